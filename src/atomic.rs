@@ -0,0 +1,89 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Write},
+    process
+};
+
+use crate::{Path, PathBuf, error::Result, vault};
+
+const BACKUP: &str = ".pokisona/backup";
+
+/// Replaces the content of a file so that a crash leaves either the old or the new version.
+///
+/// The content goes to a temporary file next to it, which is synced and renamed over the
+/// original, keeping its permissions. With `backup` the previous version is copied to
+/// `.pokisona/backup/<path>~` first
+pub fn write(path: &Path, content: &str, backup: bool) -> Result<()> {
+    let backup = backup.then(|| backup_path(path)).flatten();
+    // Renaming over a symlink would replace the link instead of the file it points to
+    let path = if path.is_symlink() {
+        path.canonicalize_utf8()?
+    } else {
+        path.to_path_buf()
+    };
+
+    let permissions = match fs::metadata(&path) {
+        Ok(metadata) => Some(metadata.permissions()),
+        Err(error) if error.kind() == ErrorKind::NotFound => None,
+        Err(error) => return Err(error.into())
+    };
+
+    if let Some(backup) = backup
+        && permissions.is_some()
+    {
+        if let Some(parent) = backup.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::copy(&path, backup)?;
+    }
+
+    let parent = match path.parent() {
+        Some(parent) if parent != "" => parent,
+        _ => Path::new(".")
+    };
+    let name = path.file_name().unwrap_or_default();
+    let temp = parent.join(format!(".{name}.{}.tmp", process::id()));
+
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        file.write_all(content.as_bytes())?;
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+
+        file.sync_all()?;
+        fs::rename(&temp, &path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+
+    result?;
+    // The rename itself only survives a crash once the directory is synced
+    File::open(parent)?.sync_all()?;
+    Ok(())
+}
+
+/// Where the previous version of a file is kept, files outside of the vault have no backup
+fn backup_path(path: &Path) -> Option<PathBuf> {
+    vault::is_inside(path).then(|| PathBuf::from(format!("{BACKUP}/{path}~")))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Path, PathBuf, atomic::backup_path};
+
+    #[test]
+    fn test_backup_path() {
+        assert_eq!(
+            backup_path(Path::new("notes/idea.md")),
+            Some(PathBuf::from(".pokisona/backup/notes/idea.md~"))
+        );
+        assert_eq!(backup_path(Path::new("/tmp/idea.md")), None);
+        assert_eq!(backup_path(Path::new("../idea.md")), None);
+    }
+}
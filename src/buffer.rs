@@ -0,0 +1,252 @@
+use std::{
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::ErrorKind,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime
+};
+
+use iced::widget::text_editor::{self, Action, Content, Cursor, Position};
+
+use crate::{Path, PathBuf, cli::Location, error::Result, git::Overview};
+
+#[derive(Clone)]
+pub struct Buffer {
+    /// Identifies the buffer for tasks that finish later, indices shift when buffers close
+    pub id: usize,
+    pub content: text_editor::Content,
+    pub kind: BufferKind,
+    /// How often the content was replaced instead of edited, so the copies drawn by other panes
+    /// know when to follow
+    pub reloads: usize,
+    /// Bumped by every change of the content
+    pub revision: usize,
+    /// The changes against the committed version along with the revision they were compared for
+    pub overview: Option<(usize, Option<Overview>)>
+}
+
+#[derive(Clone)]
+pub enum BufferKind {
+    Scratch,
+    File(File),
+    /// Generated content that cannot be edited, like the help page
+    ReadOnly(&'static str)
+}
+
+#[derive(Clone)]
+pub struct File {
+    pub edited: bool,
+    pub path: PathBuf,
+    /// The version on disk the buffer is based on, none until the file is read or written
+    pub disk: Option<Disk>
+}
+
+/// A version of a file on disk, used to notice when other programs change it
+#[derive(Clone)]
+pub struct Disk {
+    pub modified: Option<SystemTime>,
+    pub hash: u64,
+    /// The common ancestor when merging the buffer with the changes on disk
+    pub text: String
+}
+
+impl Buffer {
+    pub fn new(content: Content, kind: BufferKind) -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            content,
+            kind,
+            reloads: 0,
+            revision: 0,
+            overview: None
+        }
+    }
+
+    pub fn scratch() -> Self {
+        Self::new(Content::new(), BufferKind::Scratch)
+    }
+
+    /// The file of the buffer with the cursor of a content showing it
+    pub fn location(&self, content: &Content) -> Option<Location> {
+        let Position { line, column } = content.cursor().position;
+        Some(Location {
+            path: self.file()?.path.clone(),
+            line: Some(line + 1),
+            column: Some(column + 1)
+        })
+    }
+
+    pub fn file(&self) -> Option<&File> {
+        match &self.kind {
+            BufferKind::File(file) => Some(file),
+            _ => None
+        }
+    }
+
+    /// Performs an action on the content, edits to read-only buffers are ignored
+    pub fn perform(&mut self, action: Action) {
+        if action.is_edit() {
+            match &mut self.kind {
+                BufferKind::ReadOnly(_) => return,
+                BufferKind::File(file) => file.edited = true,
+                BufferKind::Scratch => {}
+            }
+
+            self.revision += 1;
+        }
+
+        self.content.perform(action);
+    }
+
+    /// The start and the end of the selection, or the cursor position twice if nothing is selected
+    pub fn selection_bounds(&self) -> (Position, Position) {
+        let Cursor {
+            position,
+            selection
+        } = self.content.cursor();
+        let selection = selection.unwrap_or(position);
+        if (selection.line, selection.column) < (position.line, position.column) {
+            (selection, position)
+        } else {
+            (position, selection)
+        }
+    }
+
+    pub fn is_edited(&self) -> bool {
+        self.file().is_some_and(|file| file.edited)
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        matches!(self.kind, BufferKind::ReadOnly(_))
+    }
+
+    /// Whether the buffer can be replaced without losing anything
+    pub fn is_disposable(&self) -> bool {
+        matches!(self.kind, BufferKind::Scratch) && self.content.is_empty()
+    }
+
+    /// Replaces the content with a newer version of the file, keeping the cursor where it was
+    pub fn reload(&mut self, text: &str) {
+        let Cursor { position, .. } = self.content.cursor();
+        self.content = Content::with_text(text);
+        self.content.move_to(Cursor {
+            position: clamp(&self.content, position),
+            selection: None
+        });
+        self.reloads += 1;
+        self.revision += 1;
+    }
+
+    /// The name of the buffer as shown in the bar
+    pub fn title(&self) -> String {
+        match &self.kind {
+            BufferKind::Scratch => "[scratch]".to_string(),
+            BufferKind::File(file) => file.path.to_string(),
+            BufferKind::ReadOnly(name) => format!("[{name}]")
+        }
+    }
+
+    /// The token used to pick a syntax for highlighting
+    pub fn syntax(&self) -> &str {
+        self.file()
+            .and_then(|file| file.path.extension())
+            .unwrap_or("md")
+    }
+}
+
+impl Disk {
+    pub fn new(path: &Path, text: String) -> Self {
+        Self {
+            modified: modified(path),
+            hash: hash(&text),
+            text
+        }
+    }
+
+    /// Reads the file if its content differs from this version, deleted files count as unchanged
+    pub fn changed(&mut self, path: &Path) -> Result<Option<String>> {
+        let modified = modified(path);
+        if modified.is_some() && modified == self.modified {
+            return Ok(None);
+        }
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into())
+        };
+        if hash(&text) == self.hash {
+            // Only touched, no need to read it again next time
+            self.modified = modified;
+            return Ok(None);
+        }
+
+        Ok(Some(text))
+    }
+}
+
+/// A copy of a content with the same cursor, `Content::clone` only keeps the text
+pub fn copy(content: &Content) -> Content {
+    let mut copy = Content::with_text(&content.text());
+    copy.move_to(content.cursor());
+    copy
+}
+
+/// The nearest position inside of a content, columns are byte indices so they're moved back to a
+/// character boundary
+pub fn clamp(content: &Content, position: Position) -> Position {
+    let line = position.line.min(content.line_count().saturating_sub(1));
+    let column = content.line(line).map_or(0, |line| {
+        let mut column = position.column.min(line.text.len());
+        while !line.text.is_char_boundary(column) {
+            column -= 1;
+        }
+
+        column
+    });
+    Position { line, column }
+}
+
+/// Where a line ends up after an edit replaced the lines from `first` to `last` and changed the
+/// line count by `delta`, lines inside of the edit stay inside of it
+pub fn shift_line(line: usize, first: usize, last: usize, delta: isize) -> usize {
+    if line > last {
+        line.saturating_add_signed(delta)
+    } else if line >= first {
+        line.min(last.saturating_add_signed(delta)).max(first)
+    } else {
+        line
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffer::shift_line;
+
+    #[test]
+    fn test_shift_line() {
+        // Two lines inserted on line 3
+        assert_eq!(shift_line(1, 3, 3, 2), 1);
+        assert_eq!(shift_line(3, 3, 3, 2), 3);
+        assert_eq!(shift_line(7, 3, 3, 2), 9);
+        // Lines 2 to 5 replaced by a single line
+        assert_eq!(shift_line(4, 2, 5, -3), 2);
+        assert_eq!(shift_line(6, 2, 5, -3), 3);
+        // Line 4 joined with line 3 by a backspace
+        assert_eq!(shift_line(4, 3, 4, -1), 3);
+        assert_eq!(shift_line(5, 3, 4, -1), 4);
+    }
+}
@@ -0,0 +1,103 @@
+use std::mem;
+
+use iced::{
+    Length,
+    widget::{button, column, container, row, scrollable, text, text_input}
+};
+
+use crate::{Element, Path, error::Result, theme::Palette, vault::Vaults};
+
+/// Lets the user pick a vault on startup when there's no default one
+#[derive(Clone)]
+pub struct Chooser {
+    vaults: Vaults,
+    names: Vec<String>,
+    /// Name of the vault to create
+    name: String,
+    /// Folder to open as a vault
+    folder: String
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Open(String),
+    EditName(String),
+    Create,
+    EditFolder(String),
+    Register
+}
+
+impl Chooser {
+    const SPACING: f32 = 10.0;
+    const WIDTH: f32 = 500.0;
+
+    pub fn new(vaults: Vaults) -> Result<Self> {
+        Ok(Self {
+            names: vaults.list()?,
+            vaults,
+            name: String::new(),
+            folder: String::new()
+        })
+    }
+
+    /// Returns the name of the chosen vault after making it the working directory
+    pub fn update(&mut self, message: Message) -> Result<Option<String>> {
+        let name = match message {
+            Message::Open(name) => name,
+            Message::EditName(name) => {
+                self.name = name;
+                return Ok(None);
+            }
+            Message::EditFolder(folder) => {
+                self.folder = folder;
+                return Ok(None);
+            }
+            Message::Create => {
+                self.vaults.create(&self.name)?;
+                mem::take(&mut self.name)
+            }
+            Message::Register => self.vaults.register_folder(Path::new(&self.folder))?
+        };
+
+        self.vaults.enter(&name)?;
+        Ok(Some(name))
+    }
+
+    pub fn view(&self, palette: &Palette) -> Element<'_, Message> {
+        let vaults: Element<'_, Message> = if self.names.is_empty() {
+            text("There are no vaults yet")
+                .color(palette.subtext)
+                .into()
+        } else {
+            scrollable(column(self.names.iter().map(|name| {
+                let path = text(self.vaults.path(name).to_string()).color(palette.subtext);
+                button(row![text(name), path].spacing(Self::SPACING))
+                    .on_press(Message::Open(name.clone()))
+                    .style(button::text)
+                    .width(Length::Fill)
+                    .into()
+            })))
+            .into()
+        };
+
+        let create = row![
+            text_input("New vault name", &self.name)
+                .on_input(Message::EditName)
+                .on_submit(Message::Create),
+            button("Create").on_press(Message::Create)
+        ]
+        .spacing(Self::SPACING);
+        let register = row![
+            text_input("Path to a folder", &self.folder)
+                .on_input(Message::EditFolder)
+                .on_submit(Message::Register),
+            button("Open folder").on_press(Message::Register)
+        ]
+        .spacing(Self::SPACING);
+
+        let chooser = column![text("Choose a vault").size(24), vaults, create, register]
+            .spacing(Self::SPACING)
+            .width(Self::WIDTH);
+        container(chooser).center(Length::Fill).into()
+    }
+}
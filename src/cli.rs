@@ -1,12 +1,38 @@
-use std::{env, fs};
+use std::{
+    convert::Infallible,
+    env, fs,
+    io::{self, Write},
+    process,
+    str::FromStr
+};
 
-use clap::{ArgAction, Parser, Subcommand};
-use color_eyre::{Result, eyre::OptionExt};
+use clap::{ArgAction, Args, Parser, Subcommand};
+use color_eyre::{
+    Result,
+    eyre::{OptionExt, bail, eyre}
+};
+use itertools::Itertools;
+use jiff::Zoned;
+use serde::{Deserialize, Serialize};
 
-use crate::PathBuf;
+use crate::{
+    Path, PathBuf,
+    config::Config,
+    error::Error,
+    index::{self, Index, NoteLink},
+    ipc::{self, Request},
+    vault::{self, Vaults}
+};
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
+    /// File to open, optionally as `note.md:42:7`. Its vault is the closest parent folder
+    /// containing `.pokisona`
+    file: Option<Location>,
+    /// Don't reopen the files that were open when the vault was last closed
+    #[arg(long, global = true, action = ArgAction::SetTrue)]
+    no_session: bool,
     #[command(subcommand)]
     subcommand: Option<VaultCommand>
 }
@@ -14,60 +40,479 @@ struct Cli {
 #[derive(Subcommand)]
 enum VaultCommand {
     Open {
+        /// File to open relative to the vault, optionally as `note.md:42:7`
         #[arg(long)]
-        file: Option<PathBuf>,
+        file: Option<Location>,
 
-        name: String,
+        #[arg(required_unless_present = "path", conflicts_with = "path")]
+        name: Option<String>,
+        /// Open a folder anywhere on disk, registering it as a vault named after the folder
+        #[arg(long)]
+        path: Option<PathBuf>,
         #[arg(long, action = ArgAction::SetTrue)]
         set_default: bool
     },
+    /// Delete a vault, by default it's moved to the deleted vaults folder in the data dir.
+    ///
+    /// Registered vaults are only unregistered, their folder is kept
     Delete {
-        name: String
+        name: String,
+        /// Don't ask for confirmation
+        #[arg(long, short, action = ArgAction::SetTrue)]
+        yes: bool,
+        /// Delete the vault permanently
+        #[arg(long, action = ArgAction::SetTrue)]
+        permanent: bool
+    },
+    /// List all vaults, the default one is marked with `*`
+    List,
+    /// Create a new empty vault
+    New {
+        name: String,
+        #[arg(long, action = ArgAction::SetTrue)]
+        set_default: bool
+    },
+    /// Register an existing folder anywhere on disk as a vault
+    Register { name: String, path: PathBuf },
+    /// Rename a vault, keeping it the default if it was
+    Rename { old: String, new: String },
+    /// Set the vault opened when no vault is given
+    SetDefault { name: String },
+    /// Query the links between notes
+    Query {
+        #[command(subcommand)]
+        query: Query
+    },
+    /// Search the notes for a case-insensitive pattern
+    Search {
+        pattern: String,
+        #[command(flatten)]
+        headless: Headless
+    },
+    /// List all tags along with the notes carrying them
+    Tags {
+        #[command(flatten)]
+        headless: Headless
+    },
+    /// List all links between notes
+    Links {
+        /// Only list links to notes that don't exist
+        #[arg(long, action = ArgAction::SetTrue)]
+        broken: bool,
+        #[command(flatten)]
+        headless: Headless
+    },
+    /// Run a command in the window already open in a vault, like `pokisona remote 'open note.md'`
+    Remote {
+        command: String,
+        /// Vault of the window instead of the default one
+        #[arg(long)]
+        vault: Option<String>
     }
 }
 
+#[derive(Subcommand)]
+enum Query {
+    /// List the links pointing to a note, given like a link target
+    Backlinks {
+        note: String,
+        #[command(flatten)]
+        headless: Headless
+    }
+}
+
+/// Options of the commands that run against a vault without the gui
+#[derive(Args)]
+struct Headless {
+    /// Vault to use instead of the default one
+    #[arg(long)]
+    vault: Option<String>,
+    /// Print JSON instead of plain text
+    #[arg(long, action = ArgAction::SetTrue)]
+    json: bool
+}
+
+/// A link in the output of the headless commands
+#[derive(Serialize)]
+struct LinkOutput<'a> {
+    path: &'a Path,
+    #[serde(flatten)]
+    link: &'a NoteLink
+}
+
+#[derive(Serialize)]
+struct TagOutput<'a> {
+    tag: &'a str,
+    notes: Vec<&'a Path>
+}
+
 pub struct VaultName(pub String);
-pub struct InitialFile(pub Option<PathBuf>);
-pub fn handle_args() -> Result<(VaultName, InitialFile)> {
-    let mut path = dirs::data_dir().ok_or_eyre("Cannot determine data dir")?;
-    path.push("pokisona");
-    fs::create_dir_all(&path)?;
+pub struct InitialFile(pub Option<Location>);
+pub struct RestoreSession(pub bool);
+
+/// A file with an optional one-based line and column
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub column: Option<usize>
+}
+
+impl FromStr for Location {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut path = s;
+        let mut numbers = Vec::new();
+        while numbers.len() < 2
+            && let Some((rest, number)) = path.rsplit_once(':')
+            && let Ok(number) = number.parse()
+        {
+            numbers.insert(0, number);
+            path = rest;
+        }
+
+        Ok(Self {
+            path: PathBuf::from(path),
+            line: numbers.first().copied(),
+            column: numbers.get(1).copied()
+        })
+    }
+}
+
+/// Handles the vault subcommands, returning the vault to open unless it's left to the vault chooser
+pub fn handle_args() -> Result<(Option<VaultName>, InitialFile, RestoreSession)> {
+    let mut vaults = Vaults::load()?;
     let cli = Cli::parse();
-    let (vault_name, file) = match cli.subcommand {
+    let restore_session = RestoreSession(!cli.no_session);
+    let (vault_name, file, set_default) = match cli.subcommand {
         Some(VaultCommand::Open {
+            name: Some(name),
+            set_default,
+            file,
+            ..
+        }) => (Some(name), file, set_default),
+        Some(VaultCommand::Open {
+            path: Some(path),
+            set_default,
+            file,
+            ..
+        }) => (Some(vaults.register_folder(&path)?), file, set_default),
+        Some(VaultCommand::Open { .. }) => unreachable!("clap requires a name or a path"),
+        Some(VaultCommand::Delete {
             name,
-            set_default: true,
-            file
+            yes,
+            permanent
         }) => {
-            path.push("default");
-            fs::write(&path, &name)?;
-            path.pop();
-            (name, file)
+            delete_vault(&mut vaults, &name, yes, permanent)?;
+            process::exit(0);
         }
-        Some(VaultCommand::Open {
-            name,
-            set_default: false,
-            file
-        }) => (name, file),
-        Some(VaultCommand::Delete { name }) => {
-            // TODO: create a confirmation prompt
-            path.push(name);
-            fs::remove_dir(&path)?;
-            std::process::exit(0);
-        }
-        None => {
-            path.push("default");
-            let name = fs::read_to_string(&path)?;
-            path.pop();
-            (name, None)
+        Some(VaultCommand::List) => {
+            let default = vaults.default()?;
+            for name in vaults.list()? {
+                let marker = if default.as_ref() == Some(&name) {
+                    '*'
+                } else {
+                    ' '
+                };
+                println!("{marker} {name}\t{}", vaults.path(&name));
+            }
+
+            process::exit(0);
+        }
+        Some(VaultCommand::New { name, set_default }) => {
+            vaults.create(&name)?;
+            if set_default {
+                vaults.set_default(&name)?;
+            }
+
+            process::exit(0);
+        }
+        Some(VaultCommand::Register { name, path }) => {
+            vaults.register(&name, &path)?;
+            process::exit(0);
+        }
+        Some(VaultCommand::Rename { old, new }) => {
+            vaults.rename(&old, &new)?;
+            process::exit(0);
+        }
+        Some(VaultCommand::SetDefault { name }) => {
+            vaults.set_default(&name)?;
+            process::exit(0);
+        }
+        Some(VaultCommand::Query {
+            query: Query::Backlinks { note, headless }
+        }) => {
+            let config = headless.enter(&vaults)?;
+            let index = Index::build(Path::new("."), &config.extension)?;
+            let note = index
+                .resolve(&note)
+                .ok_or_else(|| eyre!("Note {note} doesn't exist"))?;
+            print_links(headless.json, index.backlinks(note))?;
+            process::exit(0);
+        }
+        Some(VaultCommand::Search { pattern, headless }) => {
+            let config = headless.enter(&vaults)?;
+            let matches = index::search(Path::new("."), &config.extension, &pattern)?;
+            print(headless.json, &matches, |found| {
+                format!(
+                    "{}:{}:{}: {}",
+                    found.path, found.line, found.column, found.text
+                )
+            })?;
+            process::exit(0);
+        }
+        Some(VaultCommand::Tags { headless }) => {
+            let config = headless.enter(&vaults)?;
+            let index = Index::build(Path::new("."), &config.extension)?;
+            let tags: Vec<_> = index
+                .tags()
+                .into_iter()
+                .map(|(tag, notes)| TagOutput { tag, notes })
+                .collect();
+            print(headless.json, &tags, |tag| {
+                format!("#{}\t{}", tag.tag, tag.notes.iter().join(" "))
+            })?;
+            process::exit(0);
+        }
+        Some(VaultCommand::Links { broken, headless }) => {
+            let config = headless.enter(&vaults)?;
+            let index = Index::build(Path::new("."), &config.extension)?;
+            let links = if broken {
+                index.broken_links()
+            } else {
+                index.links().collect()
+            };
+
+            print_links(headless.json, links)?;
+            process::exit(0);
+        }
+        Some(VaultCommand::Remote { command, vault }) => {
+            let vault = enter_existing(&vaults, vault)?;
+            if !ipc::send(&Request::Command(command))? {
+                bail!("No window is open in vault {vault}");
+            }
+
+            process::exit(0);
+        }
+        None => match cli.file {
+            Some(location) => {
+                let (name, path) = vaults.containing(&absolute(&location.path)?)?;
+                (Some(name), Some(Location { path, ..location }), false)
+            }
+            // The vault is chosen in the gui
+            None => (vaults.default()?, None, false)
         }
     };
 
-    path.extend(["vaults", &vault_name]);
-    fs::create_dir_all(&path)?;
-    env::set_current_dir(&path)?;
+    let Some(vault_name) = vault_name else {
+        return Ok((None, InitialFile(file), restore_session));
+    };
 
-    fs::create_dir_all(".pokisona")?;
+    vaults.enter(&vault_name)?;
+    if set_default {
+        vaults.set_default(&vault_name)?;
+    }
 
-    Ok((VaultName(vault_name), InitialFile(file)))
+    // Absolute paths inside the vault are opened like vault-relative ones
+    let vault = PathBuf::try_from(env::current_dir()?)?;
+    let file = file.map(|location| match location.path.strip_prefix(&vault) {
+        Ok(path) => Location {
+            path: path.to_path_buf(),
+            ..location
+        },
+        Err(_) => location
+    });
+
+    let request = match &file {
+        Some(location) => Request::Open(location.clone()),
+        None => Request::Focus
+    };
+
+    if ipc::send(&request)? {
+        process::exit(0);
+    }
+
+    Ok((
+        Some(VaultName(vault_name)),
+        InitialFile(file),
+        restore_session
+    ))
+}
+
+impl Headless {
+    /// Makes the vault the working directory and loads its config
+    fn enter(&self, vaults: &Vaults) -> Result<Config> {
+        enter_existing(vaults, self.vault.clone())?;
+        Ok(Config::load(Some(Path::new(".")))?)
+    }
+}
+
+/// Makes the given or default vault the working directory without creating it
+fn enter_existing(vaults: &Vaults, name: Option<String>) -> Result<String> {
+    let name = match name {
+        Some(name) => name,
+        None => vaults
+            .default()?
+            .ok_or_eyre("No default vault set, choose one with --vault")?
+    };
+
+    if !vaults.exists(&name) {
+        return Err(Error::VaultDoesntExist(name).into());
+    }
+
+    vaults.enter(&name)?;
+    Ok(name)
+}
+
+/// Prints items as a JSON array or one per line
+fn print<T: Serialize>(json: bool, items: &[T], line: impl Fn(&T) -> String) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(items)?);
+    } else {
+        for item in items {
+            println!("{}", line(item));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_links(json: bool, links: Vec<(&Path, &NoteLink)>) -> Result<()> {
+    let links: Vec<_> = links
+        .into_iter()
+        .map(|(path, link)| LinkOutput { path, link })
+        .collect();
+    print(json, &links, |LinkOutput { path, link }| {
+        format!("{path}:{}:{}\t[[{}]]", link.line, link.column, link.target)
+    })
+}
+
+/// Makes a path absolute without requiring the file to exist
+fn absolute(path: &Path) -> Result<PathBuf> {
+    let parent = match path.parent() {
+        Some(parent) if parent != "" => parent,
+        _ => Path::new(".")
+    };
+
+    let name = path
+        .file_name()
+        .ok_or_eyre("Path doesn't point to a file")?;
+    Ok(parent.canonicalize_utf8()?.join(name))
+}
+
+fn delete_vault(vaults: &mut Vaults, name: &str, yes: bool, permanent: bool) -> Result<()> {
+    vault::validate_name(name)?;
+    if vaults.is_registered(name) {
+        let path = vaults.unregister(name)?;
+        println!("Unregistered vault {name}, its files in {path} were kept");
+        return Ok(());
+    }
+
+    let vault = vaults.path(name);
+    if !vault.is_dir() {
+        bail!("Vault {name} doesn't exist");
+    }
+
+    let extension = Config::load(Some(&vault)).unwrap_or_default().extension;
+    let notes = vault::files(&vault)?
+        .iter()
+        .filter(|path| vault::is_note(path, &extension))
+        .count();
+    let size = human_size(vault::size(&vault)?);
+    println!("Vault {name} at {vault} contains {notes} notes ({size})");
+    if !yes {
+        let action = if permanent {
+            "permanently delete"
+        } else {
+            "delete"
+        };
+        print!("Type the name of the vault to {action} it: ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if answer.trim() != name {
+            bail!("Confirmation doesn't match, vault was not deleted");
+        }
+    }
+
+    if permanent {
+        fs::remove_dir_all(&vault)?;
+        println!("Deleted vault {name}");
+    } else {
+        let deleted = vaults.data_dir().join("deleted");
+        fs::create_dir_all(&deleted)?;
+        let target = deleted.join(format!("{name}-{}", Zoned::now().strftime("%Y%m%d-%H%M%S")));
+        fs::rename(&vault, &target)?;
+        println!("Moved vault {name} to {target}");
+    }
+
+    vaults.clear_default(name)?;
+    Ok(())
+}
+
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use crate::{
+        PathBuf,
+        cli::{Location, delete_vault},
+        vault::Vaults
+    };
+
+    #[test]
+    fn test_parse_location() {
+        let location = |path: &str, line, column| Location {
+            path: path.into(),
+            line,
+            column
+        };
+        assert_eq!("note.md".parse(), Ok(location("note.md", None, None)));
+        assert_eq!(
+            "note.md:42".parse(),
+            Ok(location("note.md", Some(42), None))
+        );
+        assert_eq!(
+            "a/note.md:42:7".parse(),
+            Ok(location("a/note.md", Some(42), Some(7)))
+        );
+        assert_eq!("a:b.md:3".parse(), Ok(location("a:b.md", Some(3), None)));
+        assert_eq!("1:2:3".parse(), Ok(location("1", Some(2), Some(3))));
+    }
+
+    #[test]
+    fn test_delete_invalid_vault_name() {
+        let data_dir = PathBuf::try_from(env::temp_dir())
+            .unwrap()
+            .join(format!("pokisona-vaults-{}", process::id()));
+        let note = data_dir.join("vaults/notes/note.md");
+        fs::create_dir_all(note.parent().unwrap()).unwrap();
+        fs::write(&note, "").unwrap();
+        let mut vaults = Vaults::at(data_dir.clone()).unwrap();
+
+        for name in ["", ".", "..", "a/b", "notes/.."] {
+            assert!(delete_vault(&mut vaults, name, true, true).is_err());
+            assert!(vaults.rename(name, "other").is_err());
+        }
+        assert!(note.exists());
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
 }
@@ -1,10 +1,10 @@
-use std::str::FromStr;
+use std::{collections::HashMap, fmt::Display, str::FromStr, vec};
 
 use chumsky::{prelude::*, text::whitespace};
 
 use crate::PathBuf;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Command {
     Quit,
     ForceQuit,
@@ -13,10 +13,676 @@ pub enum Command {
     WriteQuit(Option<PathBuf>),
     ForceWriteQuit(Option<PathBuf>),
     Reload,
+    Diff,
+    Merge,
+    History,
+    Tags,
     Remove,
+    ForceRemove,
+    TrashList,
+    TrashRestore(String),
+    TrashEmpty,
     Open(PathBuf),
     Move(PathBuf),
-    ForceMove(PathBuf)
+    ForceMove(PathBuf),
+    Rename(PathBuf, PathBuf),
+    BufferNext,
+    BufferPrevious,
+    BufferClose,
+    ForceBufferClose,
+    VerticalSplit,
+    HorizontalSplit,
+    WindowClose,
+    WindowOnly,
+    WindowNext,
+    WindowLeft,
+    WindowDown,
+    WindowUp,
+    WindowRight,
+    TabNew(Option<PathBuf>),
+    TabClose,
+    TabNext,
+    TabPrevious,
+    Help(Option<String>),
+    Alias(String, String),
+    Shell(String),
+    Pipe(String),
+    InsertOutput(String),
+    AppendOutput(String),
+    New(Option<String>),
+    Scratch,
+    Explorer,
+    ExplorerFocus,
+    Theme(String),
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    Zoom(f32),
+    GitCommit(Option<String>),
+    GitPull,
+    GitPush
+}
+
+type Args = vec::IntoIter<String>;
+
+/// Metadata of a command, used both for parsing and for generating help
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub args: &'static str,
+    pub description: &'static str,
+    /// Description of the `!` variant, if the command has one
+    pub force: Option<&'static str>,
+    /// Whether the rest of the line, including `|`, is passed as a single argument
+    pub raw: bool,
+    parse: fn(&mut Args, bool) -> Result<Command, CommandParseErr>
+}
+
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "quit",
+        aliases: &["q"],
+        args: "",
+        description: "Quit pokisona",
+        force: Some("Quit even if there are unsaved buffers"),
+        raw: false,
+        parse: |_, force| {
+            Ok(if force {
+                Command::ForceQuit
+            } else {
+                Command::Quit
+            })
+        }
+    },
+    CommandInfo {
+        name: "write",
+        aliases: &["w"],
+        args: "[path]",
+        description: "Write the buffer to its file, or to path if given",
+        force: Some(
+            "Write even if the file changed on disk and create the parent directory if it doesn't exist"
+        ),
+        raw: false,
+        parse: |args, force| {
+            let path = args.next().map(PathBuf::from);
+            Ok(if force {
+                Command::ForceWrite(path)
+            } else {
+                Command::Write(path)
+            })
+        }
+    },
+    CommandInfo {
+        name: "write-quit",
+        aliases: &["wq", "x"],
+        args: "[path]",
+        description: "Write the buffer and quit",
+        force: Some(
+            "Write and quit even if the file changed on disk, creating the parent directory if it doesn't exist"
+        ),
+        raw: false,
+        parse: |args, force| {
+            let path = args.next().map(PathBuf::from);
+            Ok(if force {
+                Command::ForceWriteQuit(path)
+            } else {
+                Command::WriteQuit(path)
+            })
+        }
+    },
+    CommandInfo {
+        name: "open",
+        aliases: &["o"],
+        args: "<path>",
+        description: "Open a file in a new buffer",
+        force: None,
+        raw: false,
+        parse: |args, _| Ok(Command::Open(required(args)?.into()))
+    },
+    CommandInfo {
+        name: "reload",
+        aliases: &["rl"],
+        args: "",
+        description: "Discard changes and read the file again",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::Reload)
+    },
+    CommandInfo {
+        name: "diff",
+        aliases: &[],
+        args: "",
+        description: "Compare the changes in the buffer with the changes made to its file by another program",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::Diff)
+    },
+    CommandInfo {
+        name: "merge",
+        aliases: &[],
+        args: "",
+        description: "Merge the changes made to the file by another program into the buffer",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::Merge)
+    },
+    CommandInfo {
+        name: "history",
+        aliases: &[],
+        args: "",
+        description: "Browse the snapshots taken every time the file was written and restore one",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::History)
+    },
+    CommandInfo {
+        name: "tags",
+        aliases: &[],
+        args: "",
+        description: "Browse the tags of the vault and open a note carrying a tag or its children",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::Tags)
+    },
+    CommandInfo {
+        name: "remove",
+        aliases: &["rm"],
+        args: "",
+        description: "Move the file of the buffer to the vault trash and close the buffer",
+        force: Some("Delete the file permanently instead, discarding unsaved changes"),
+        raw: false,
+        parse: |_, force| {
+            Ok(if force {
+                Command::ForceRemove
+            } else {
+                Command::Remove
+            })
+        }
+    },
+    CommandInfo {
+        name: "trash-list",
+        aliases: &[],
+        args: "",
+        description: "List the files in the vault trash",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::TrashList)
+    },
+    CommandInfo {
+        name: "trash-restore",
+        aliases: &[],
+        args: "<file>",
+        description: "Restore a file from the trash by its name in the trash or its original path and open it",
+        force: None,
+        raw: false,
+        parse: |args, _| Ok(Command::TrashRestore(required(args)?))
+    },
+    CommandInfo {
+        name: "trash-empty",
+        aliases: &[],
+        args: "",
+        description: "Permanently delete everything in the trash",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::TrashEmpty)
+    },
+    CommandInfo {
+        name: "move",
+        aliases: &["mv"],
+        args: "<path>",
+        description: "Move the file of the buffer to path",
+        force: Some("Move and create the parent directories if they don't exist"),
+        raw: false,
+        parse: |args, force| {
+            let path = required(args)?.into();
+            Ok(if force {
+                Command::ForceMove(path)
+            } else {
+                Command::Move(path)
+            })
+        }
+    },
+    CommandInfo {
+        name: "rename",
+        aliases: &[],
+        args: "<path> <new-path>",
+        description: "Move a file or folder of the vault, updating the links to the notes in it",
+        force: None,
+        raw: false,
+        parse: |args, _| {
+            Ok(Command::Rename(
+                required(args)?.into(),
+                required(args)?.into()
+            ))
+        }
+    },
+    CommandInfo {
+        name: "buffer-next",
+        aliases: &["bn"],
+        args: "",
+        description: "Switch to the next buffer",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::BufferNext)
+    },
+    CommandInfo {
+        name: "buffer-previous",
+        aliases: &["bp"],
+        args: "",
+        description: "Switch to the previous buffer",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::BufferPrevious)
+    },
+    CommandInfo {
+        name: "buffer-close",
+        aliases: &["bc"],
+        args: "",
+        description: "Close the buffer",
+        force: Some("Close the buffer even if it has unsaved changes"),
+        raw: false,
+        parse: |_, force| {
+            Ok(if force {
+                Command::ForceBufferClose
+            } else {
+                Command::BufferClose
+            })
+        }
+    },
+    CommandInfo {
+        name: "vsplit",
+        aliases: &["vs"],
+        args: "",
+        description: "Split the pane into two side by side showing the same buffer",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::VerticalSplit)
+    },
+    CommandInfo {
+        name: "hsplit",
+        aliases: &["split", "sp"],
+        args: "",
+        description: "Split the pane into two above each other showing the same buffer",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::HorizontalSplit)
+    },
+    CommandInfo {
+        name: "window-close",
+        aliases: &["close"],
+        args: "",
+        description: "Close the focused pane, its buffer stays open",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::WindowClose)
+    },
+    CommandInfo {
+        name: "window-only",
+        aliases: &["only"],
+        args: "",
+        description: "Close every pane except the focused one",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::WindowOnly)
+    },
+    CommandInfo {
+        name: "window-next",
+        aliases: &[],
+        args: "",
+        description: "Focus the next pane",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::WindowNext)
+    },
+    CommandInfo {
+        name: "window-left",
+        aliases: &[],
+        args: "",
+        description: "Focus the pane to the left",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::WindowLeft)
+    },
+    CommandInfo {
+        name: "window-down",
+        aliases: &[],
+        args: "",
+        description: "Focus the pane below",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::WindowDown)
+    },
+    CommandInfo {
+        name: "window-up",
+        aliases: &[],
+        args: "",
+        description: "Focus the pane above",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::WindowUp)
+    },
+    CommandInfo {
+        name: "window-right",
+        aliases: &[],
+        args: "",
+        description: "Focus the pane to the right",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::WindowRight)
+    },
+    CommandInfo {
+        name: "tab-new",
+        aliases: &[],
+        args: "[path]",
+        description: "Open a tab with a scratch buffer or the given file",
+        force: None,
+        raw: false,
+        parse: |args, _| Ok(Command::TabNew(args.next().map(PathBuf::from)))
+    },
+    CommandInfo {
+        name: "tab-close",
+        aliases: &[],
+        args: "",
+        description: "Close the tab, its buffers stay open",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::TabClose)
+    },
+    CommandInfo {
+        name: "tab-next",
+        aliases: &[],
+        args: "",
+        description: "Switch to the next tab",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::TabNext)
+    },
+    CommandInfo {
+        name: "tab-previous",
+        aliases: &[],
+        args: "",
+        description: "Switch to the previous tab",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::TabPrevious)
+    },
+    CommandInfo {
+        name: "help",
+        aliases: &["h"],
+        args: "[command]",
+        description: "Show help for all commands and keybindings, or for a single command",
+        force: None,
+        raw: false,
+        parse: |args, _| Ok(Command::Help(args.next()))
+    },
+    CommandInfo {
+        name: "alias",
+        aliases: &[],
+        args: "<name> <command>",
+        description: "Define an alias for a command line for this session, aliases can also be set in the config file",
+        force: None,
+        raw: false,
+        parse: |args, _| Ok(Command::Alias(required(args)?, required(args)?))
+    },
+    CommandInfo {
+        name: "shell",
+        aliases: &["sh"],
+        args: "<command>",
+        description: "Run a shell command in the vault root and show its output in a scratch buffer",
+        force: None,
+        raw: true,
+        parse: |args, _| Ok(Command::Shell(required(args)?))
+    },
+    CommandInfo {
+        name: "pipe",
+        aliases: &[],
+        args: "<command>",
+        description: "Replace the selection with the output of a shell command reading the selection",
+        force: None,
+        raw: true,
+        parse: |args, _| Ok(Command::Pipe(required(args)?))
+    },
+    CommandInfo {
+        name: "insert-output",
+        aliases: &[],
+        args: "<command>",
+        description: "Insert the output of a shell command before the selection",
+        force: None,
+        raw: true,
+        parse: |args, _| Ok(Command::InsertOutput(required(args)?))
+    },
+    CommandInfo {
+        name: "append-output",
+        aliases: &[],
+        args: "<command>",
+        description: "Insert the output of a shell command after the selection",
+        force: None,
+        raw: true,
+        parse: |args, _| Ok(Command::AppendOutput(required(args)?))
+    },
+    CommandInfo {
+        name: "new",
+        aliases: &["n"],
+        args: "[title]",
+        description: "Create a note from a title in the new notes folder and open it",
+        force: None,
+        raw: true,
+        parse: |args, _| Ok(Command::New(args.next()))
+    },
+    CommandInfo {
+        name: "scratch",
+        aliases: &[],
+        args: "",
+        description: "Open a new scratch buffer",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::Scratch)
+    },
+    CommandInfo {
+        name: "explorer",
+        aliases: &[],
+        args: "",
+        description: "Show or hide the file explorer",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::Explorer)
+    },
+    CommandInfo {
+        name: "explorer-focus",
+        aliases: &[],
+        args: "",
+        description: "Show the file explorer with the open file selected and switch to panel mode",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::ExplorerFocus)
+    },
+    CommandInfo {
+        name: "theme",
+        aliases: &[],
+        args: "<name>",
+        description: "Switch to latte, frappe, macchiato, mocha, a theme from the config or auto to follow the desktop",
+        force: None,
+        raw: false,
+        parse: |args, _| Ok(Command::Theme(required(args)?))
+    },
+    CommandInfo {
+        name: "zoom-in",
+        aliases: &[],
+        args: "",
+        description: "Make everything bigger",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::ZoomIn)
+    },
+    CommandInfo {
+        name: "zoom-out",
+        aliases: &[],
+        args: "",
+        description: "Make everything smaller",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::ZoomOut)
+    },
+    CommandInfo {
+        name: "zoom-reset",
+        aliases: &[],
+        args: "",
+        description: "Reset the zoom to the scale from the config",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::ZoomReset)
+    },
+    CommandInfo {
+        name: "zoom",
+        aliases: &[],
+        args: "<factor>",
+        description: "Set the zoom, 1 is the default size",
+        force: None,
+        raw: false,
+        parse: |args, _| {
+            required(args)?
+                .parse()
+                .ok()
+                .filter(|factor: &f32| factor.is_finite() && *factor > 0.0)
+                .map(Command::Zoom)
+                .ok_or(CommandParseErr::CannotParse)
+        }
+    },
+    CommandInfo {
+        name: "git-commit",
+        aliases: &[],
+        args: "[message]",
+        description: "Commit all changes in the vault",
+        force: None,
+        raw: true,
+        parse: |args, _| Ok(Command::GitCommit(args.next()))
+    },
+    CommandInfo {
+        name: "git-pull",
+        aliases: &[],
+        args: "",
+        description: "Merge the changes from the remote set in the config",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::GitPull)
+    },
+    CommandInfo {
+        name: "git-push",
+        aliases: &[],
+        args: "",
+        description: "Push the commits to the remote set in the config",
+        force: None,
+        raw: false,
+        parse: |_, _| Ok(Command::GitPush)
+    }
+];
+
+impl CommandInfo {
+    fn build(&self, force: bool, args: Vec<String>) -> Result<Command, CommandParseErr> {
+        if force && self.force.is_none() {
+            return Err(CommandParseErr::Unknown);
+        }
+
+        let mut args = args.into_iter();
+        let command = (self.parse)(&mut args, force)?;
+        if args.next().is_some() {
+            return Err(CommandParseErr::TooManyArgs);
+        }
+
+        Ok(command)
+    }
+}
+
+fn required(args: &mut Args) -> Result<String, CommandParseErr> {
+    args.next().ok_or(CommandParseErr::NotEnoughArgs)
+}
+
+/// Finds a command by its name or one of its aliases
+pub fn lookup(name: &str) -> Option<&'static CommandInfo> {
+    COMMANDS
+        .iter()
+        .find(|info| info.name == name || info.aliases.contains(&name))
+}
+
+/// Resolves command lines into commands, expanding user defined aliases
+#[derive(Clone, Default)]
+pub struct CommandRegistry {
+    aliases: HashMap<String, String>
+}
+
+impl CommandRegistry {
+    const MAX_ALIAS_DEPTH: usize = 16;
+
+    pub fn new(aliases: HashMap<String, String>) -> Result<Self, CommandParseErr> {
+        let mut registry = Self::default();
+        for (name, command) in aliases {
+            registry.add_alias(name, command)?;
+        }
+
+        Ok(registry)
+    }
+
+    pub fn add_alias(&mut self, name: String, command: String) -> Result<(), CommandParseErr> {
+        if ident().parse(name.as_str()).has_errors() {
+            return Err(CommandParseErr::InvalidAliasName);
+        }
+
+        if lookup(&name).is_some() {
+            return Err(CommandParseErr::AliasShadowsCommand);
+        }
+
+        self.aliases.insert(name, command);
+        Ok(())
+    }
+
+    pub fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.aliases
+            .iter()
+            .map(|(name, command)| (name.as_str(), command.as_str()))
+    }
+
+    /// Parses a `|` separated sequence of commands
+    pub fn parse(&self, line: &str) -> Result<Vec<Command>, CommandParseErr> {
+        self.expand(line, 0)
+    }
+
+    fn expand(&self, line: &str, depth: usize) -> Result<Vec<Command>, CommandParseErr> {
+        let invocations = sequence_parser()
+            .parse(line)
+            .into_output()
+            .ok_or(CommandParseErr::CannotParse)?;
+        let mut commands = Vec::new();
+        for ((name, force), args) in invocations {
+            if let Some(info) = lookup(name) {
+                commands.push(info.build(force, args)?);
+                continue;
+            }
+
+            let alias = self.alias(name).ok_or(CommandParseErr::Unknown)?;
+            if force {
+                return Err(CommandParseErr::Unknown);
+            }
+
+            if depth == Self::MAX_ALIAS_DEPTH {
+                return Err(CommandParseErr::RecursiveAlias);
+            }
+
+            let mut line = alias.to_string();
+            for arg in args {
+                line.push_str(" \"");
+                line.push_str(&arg.replace('\\', "\\\\").replace('"', "\\\""));
+                line.push('"');
+            }
+
+            commands.extend(self.expand(&line, depth + 1)?);
+        }
+
+        Ok(commands)
+    }
 }
 
 fn escape<'a>() -> impl Parser<'a, &'a str, char> {
@@ -31,11 +697,16 @@ fn ident<'a>() -> impl Parser<'a, &'a str, &'a str> {
         .to_slice()
 }
 
+fn name<'a>() -> impl Parser<'a, &'a str, (&'a str, bool)> {
+    ident().then(just('!').or_not().map(|bang| bang.is_some()))
+}
+
 // TODO: maybe this could be simplified
-fn args_parser<'a>() -> impl Parser<'a, &'a str, (&'a str, Vec<String>)> {
-    ident()
+fn args_parser<'a>() -> impl Parser<'a, &'a str, ((&'a str, bool), Vec<String>)> {
+    name()
         .then(
             whitespace()
+                .at_least(1)
                 .ignore_then(choice((
                     none_of("\\\"")
                         .or(escape())
@@ -50,7 +721,7 @@ fn args_parser<'a>() -> impl Parser<'a, &'a str, (&'a str, Vec<String>)> {
                         .delimited_by(just('\''), just('\''))
                         .boxed(),
                     any()
-                        .filter(|c: &char| !c.is_ascii_whitespace())
+                        .filter(|c: &char| !c.is_ascii_whitespace() && *c != '|')
                         .repeated()
                         .at_least(1)
                         .to_slice()
@@ -63,16 +734,48 @@ fn args_parser<'a>() -> impl Parser<'a, &'a str, (&'a str, Vec<String>)> {
                 .then_ignore(whitespace())
                 .boxed()
         )
-        .or(ident()
-            .then_ignore(whitespace())
-            .map(|ident| (ident, vec![])))
+        .or(name().then_ignore(whitespace()).map(|name| (name, vec![])))
+}
+
+/// Parses commands that take the rest of the line as is
+fn raw_args_parser<'a>() -> impl Parser<'a, &'a str, ((&'a str, bool), Vec<String>)> {
+    name()
+        .filter(|(name, _)| lookup(name).is_some_and(|info| info.raw))
+        .then(
+            whitespace()
+                .at_least(1)
+                .ignore_then(any().repeated().at_least(1).to_slice())
+                .or_not()
+        )
+        .map(|(name, rest): (_, Option<&str>)| {
+            let args = rest
+                .map(str::trim_end)
+                .filter(|rest| !rest.is_empty())
+                .map(ToString::to_string);
+            (name, args.into_iter().collect())
+        })
+}
+
+fn invocation_parser<'a>() -> impl Parser<'a, &'a str, ((&'a str, bool), Vec<String>)> {
+    choice((raw_args_parser(), args_parser()))
+}
+
+fn sequence_parser<'a>() -> impl Parser<'a, &'a str, Vec<((&'a str, bool), Vec<String>)>> {
+    invocation_parser()
+        .padded()
+        .separated_by(just('|'))
+        .at_least(1)
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use chumsky::Parser;
 
-    use crate::command::args_parser;
+    use crate::{
+        PathBuf,
+        command::{COMMANDS, Command, CommandParseErr, CommandRegistry, args_parser}
+    };
 
     #[test]
     fn test_parse_command() {
@@ -80,8 +783,110 @@ mod tests {
         dbg!(parsed.errors().collect::<Vec<_>>());
         assert_eq!(
             parsed.into_output().unwrap(),
-            ("foo-bar", vec!["baz'".to_string(), "baz".to_string()])
+            (
+                ("foo-bar", false),
+                vec!["baz'".to_string(), "baz".to_string()]
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_force() {
+        assert_eq!("q!".parse::<Command>().unwrap(), Command::ForceQuit);
+        assert_eq!(
+            "w! foo/bar.md".parse::<Command>().unwrap(),
+            Command::ForceWrite(Some(PathBuf::from("foo/bar.md")))
+        );
+        assert!(matches!(
+            "rl!".parse::<Command>(),
+            Err(CommandParseErr::Unknown)
+        ));
+    }
+
+    #[test]
+    fn test_parse_zoom() {
+        assert_eq!("zoom 1.5".parse::<Command>().unwrap(), Command::Zoom(1.5));
+        for factor in ["nan", "inf", "0", "-1"] {
+            assert!(matches!(
+                format!("zoom {factor}").parse::<Command>(),
+                Err(CommandParseErr::CannotParse)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_sequence() {
+        let registry = CommandRegistry::default();
+        assert_eq!(
+            registry.parse("w | open 'a|b.md'|q").unwrap(),
+            vec![
+                Command::Write(None),
+                Command::Open(PathBuf::from("a|b.md")),
+                Command::Quit
+            ]
+        );
+        assert!(matches!(
+            registry.parse("w | foo"),
+            Err(CommandParseErr::Unknown)
+        ));
+    }
+
+    #[test]
+    fn test_parse_raw() {
+        let registry = CommandRegistry::default();
+        assert_eq!(
+            registry.parse("w | pipe sort -r | uniq ").unwrap(),
+            vec![
+                Command::Write(None),
+                Command::Pipe("sort -r | uniq".to_string())
+            ]
         );
+        assert!(matches!(
+            registry.parse("sh"),
+            Err(CommandParseErr::NotEnoughArgs)
+        ));
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        let registry = CommandRegistry::new(
+            [
+                ("save-index", "w | open index.md"),
+                ("o-daily", "open"),
+                ("loop", "loop")
+            ]
+            .map(|(name, command)| (name.to_string(), command.to_string()))
+            .into()
+        )
+        .unwrap();
+        assert_eq!(
+            registry.parse("save-index | o-daily 'my note.md'").unwrap(),
+            vec![
+                Command::Write(None),
+                Command::Open(PathBuf::from("index.md")),
+                Command::Open(PathBuf::from("my note.md"))
+            ]
+        );
+        assert!(matches!(
+            registry.parse("loop"),
+            Err(CommandParseErr::RecursiveAlias)
+        ));
+        assert!(matches!(
+            CommandRegistry::default().add_alias("w".to_string(), "q".to_string()),
+            Err(CommandParseErr::AliasShadowsCommand)
+        ));
+    }
+
+    #[test]
+    fn test_names_are_unique() {
+        let mut names: Vec<_> = COMMANDS
+            .iter()
+            .flat_map(|info| info.aliases.iter().chain([&info.name]))
+            .collect();
+        let len = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), len);
     }
 }
 
@@ -90,43 +895,97 @@ pub enum CommandParseErr {
     TooManyArgs,
     NotEnoughArgs,
     CannotParse,
-    Unknown
+    Unknown,
+    InvalidAliasName,
+    AliasShadowsCommand,
+    RecursiveAlias
 }
 
 impl FromStr for Command {
     type Err = CommandParseErr;
 
     fn from_str(str: &str) -> Result<Self, Self::Err> {
-        let (name, args) = args_parser()
+        let ((name, force), args) = invocation_parser()
             .parse(str)
             .into_output()
             .ok_or(CommandParseErr::CannotParse)?;
-        let mut args = args.into_iter();
-        let command = match name {
-            "q" | "quit" => Command::Quit,
-            "q!" | "quit!" => Command::ForceQuit,
-            "w" | "write" => Command::Write(args.next().map(PathBuf::from)),
-            "w!" | "write!" => Command::ForceWrite(args.next().map(PathBuf::from)),
-            "wq" | "x" | "write-quit" => Command::WriteQuit(args.next().map(PathBuf::from)),
-            "wq!" | "x!" | "write-quit!" => Command::ForceWriteQuit(args.next().map(PathBuf::from)),
-            "o" | "open" => {
-                Command::Open(args.next().ok_or(CommandParseErr::NotEnoughArgs)?.into())
-            }
-            "rl" | "reload" => Command::Reload,
-            "rm" | "remove" => Command::Remove,
-            "mv" | "move" => {
-                Command::Move(args.next().ok_or(CommandParseErr::NotEnoughArgs)?.into())
-            }
-            "mv!" | "move!" => {
-                Command::ForceMove(args.next().ok_or(CommandParseErr::NotEnoughArgs)?.into())
-            }
-            _ => return Err(CommandParseErr::Unknown)
+        lookup(name)
+            .ok_or(CommandParseErr::Unknown)?
+            .build(force, args)
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn path(path: &Option<PathBuf>) -> Vec<&str> {
+            path.iter().map(|path| path.as_str()).collect()
+        }
+
+        let (name, args): (_, Vec<&str>) = match self {
+            Command::Quit => ("quit", vec![]),
+            Command::ForceQuit => ("quit!", vec![]),
+            Command::Write(file) => ("write", path(file)),
+            Command::ForceWrite(file) => ("write!", path(file)),
+            Command::WriteQuit(file) => ("write-quit", path(file)),
+            Command::ForceWriteQuit(file) => ("write-quit!", path(file)),
+            Command::Reload => ("reload", vec![]),
+            Command::Diff => ("diff", vec![]),
+            Command::Merge => ("merge", vec![]),
+            Command::History => ("history", vec![]),
+            Command::Tags => ("tags", vec![]),
+            Command::Remove => ("remove", vec![]),
+            Command::ForceRemove => ("remove!", vec![]),
+            Command::TrashList => ("trash-list", vec![]),
+            Command::TrashRestore(file) => ("trash-restore", vec![file.as_str()]),
+            Command::TrashEmpty => ("trash-empty", vec![]),
+            Command::Open(path) => ("open", vec![path.as_str()]),
+            Command::Move(path) => ("move", vec![path.as_str()]),
+            Command::ForceMove(path) => ("move!", vec![path.as_str()]),
+            Command::Rename(path, new_path) => ("rename", vec![path.as_str(), new_path.as_str()]),
+            Command::BufferNext => ("buffer-next", vec![]),
+            Command::BufferPrevious => ("buffer-previous", vec![]),
+            Command::BufferClose => ("buffer-close", vec![]),
+            Command::ForceBufferClose => ("buffer-close!", vec![]),
+            Command::VerticalSplit => ("vsplit", vec![]),
+            Command::HorizontalSplit => ("hsplit", vec![]),
+            Command::WindowClose => ("window-close", vec![]),
+            Command::WindowOnly => ("window-only", vec![]),
+            Command::WindowNext => ("window-next", vec![]),
+            Command::WindowLeft => ("window-left", vec![]),
+            Command::WindowDown => ("window-down", vec![]),
+            Command::WindowUp => ("window-up", vec![]),
+            Command::WindowRight => ("window-right", vec![]),
+            Command::TabNew(path) => ("tab-new", path.iter().map(|path| path.as_str()).collect()),
+            Command::TabClose => ("tab-close", vec![]),
+            Command::TabNext => ("tab-next", vec![]),
+            Command::TabPrevious => ("tab-previous", vec![]),
+            Command::Help(command) => ("help", command.iter().map(String::as_str).collect()),
+            Command::Alias(name, command) => ("alias", vec![name.as_str(), command.as_str()]),
+            Command::Shell(command) => return write!(f, "shell {command}"),
+            Command::Pipe(command) => return write!(f, "pipe {command}"),
+            Command::InsertOutput(command) => return write!(f, "insert-output {command}"),
+            Command::AppendOutput(command) => return write!(f, "append-output {command}"),
+            Command::New(Some(title)) => return write!(f, "new {title}"),
+            Command::New(None) => ("new", vec![]),
+            Command::Scratch => ("scratch", vec![]),
+            Command::Explorer => ("explorer", vec![]),
+            Command::ExplorerFocus => ("explorer-focus", vec![]),
+            Command::Theme(name) => ("theme", vec![name.as_str()]),
+            Command::ZoomIn => ("zoom-in", vec![]),
+            Command::ZoomOut => ("zoom-out", vec![]),
+            Command::ZoomReset => ("zoom-reset", vec![]),
+            Command::Zoom(factor) => return write!(f, "zoom {factor}"),
+            Command::GitCommit(Some(message)) => return write!(f, "git-commit {message}"),
+            Command::GitCommit(None) => ("git-commit", vec![]),
+            Command::GitPull => ("git-pull", vec![]),
+            Command::GitPush => ("git-push", vec![])
         };
 
-        if args.next().is_some() {
-            return Err(CommandParseErr::TooManyArgs);
+        f.write_str(name)?;
+        for arg in args {
+            write!(f, " {arg:?}")?;
         }
 
-        Ok(command)
+        Ok(())
     }
 }
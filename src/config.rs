@@ -0,0 +1,266 @@
+use std::{collections::HashMap, fs, io::ErrorKind, ops::RangeInclusive};
+
+use serde::Deserialize;
+
+use crate::{
+    Path, PathBuf,
+    error::{Error, Result},
+    theme::{self, Palette}
+};
+
+/// Zoom levels that keep the ui usable
+pub const SCALE_RANGE: RangeInclusive<f32> = 0.25..=4.0;
+
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Command aliases, the value can be any command line including `|` sequences
+    pub aliases: HashMap<String, String>,
+    pub new_note: NewNoteConfig,
+    pub editor: EditorConfig,
+    pub autosave: AutosaveConfig,
+    /// Keep the previous version of written files in `.pokisona/backup`
+    pub backup: bool,
+    pub history: HistoryConfig,
+    pub git: GitConfig,
+    /// Initial zoom of the whole window
+    pub scale: f32,
+    /// Extension of the files treated as notes
+    pub extension: String,
+    /// A built-in theme, one from `themes` or `auto` to follow the desktop
+    pub theme: String,
+    pub themes: HashMap<String, Palette>
+}
+
+#[derive(Clone, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NewNoteConfig {
+    /// Vault relative folder for notes created with `:new`
+    pub folder: PathBuf,
+    /// Vault relative path of a note used as the initial content, `{{title}}` is replaced by the title
+    pub template: Option<PathBuf>
+}
+
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct EditorConfig {
+    pub line_width: f32,
+    pub padding: f32,
+    /// Overrides the highlighter theme of the ui theme
+    pub highlighter_theme: Option<HighlighterTheme>
+}
+
+/// Writing edited files without `:w`, after the delay, on focus loss and on buffer switches
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutosaveConfig {
+    pub enabled: bool,
+    /// Seconds without edits before the files are written
+    pub delay: f32
+}
+
+/// Snapshots of files taken on every write, browsed with `:history`
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    /// Snapshots older than this many days are deleted
+    pub days: u32,
+    /// Snapshots kept per file
+    pub snapshots: usize
+}
+
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GitConfig {
+    /// Remote used by `:git-pull` and `:git-push`, a name or a url
+    pub remote: String,
+    /// Commit files every time they are written
+    pub auto_commit: bool,
+    /// Show where the buffer differs from the committed version in a bar next to the editor
+    pub overview: bool
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum HighlighterTheme {
+    SolarizedDark,
+    Base16Ocean,
+    Base16Mocha,
+    Base16Eighties,
+    InspiredGithub
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            aliases: HashMap::new(),
+            new_note: NewNoteConfig::default(),
+            editor: EditorConfig::default(),
+            autosave: AutosaveConfig::default(),
+            backup: false,
+            history: HistoryConfig::default(),
+            git: GitConfig::default(),
+            scale: 1.0,
+            extension: "md".to_string(),
+            theme: "auto".to_string(),
+            themes: HashMap::new()
+        }
+    }
+}
+
+impl Default for EditorConfig {
+    fn default() -> Self {
+        Self {
+            line_width: 700.0,
+            padding: 5.0,
+            highlighter_theme: None
+        }
+    }
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            delay: 2.0
+        }
+    }
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            days: 30,
+            snapshots: 100
+        }
+    }
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            remote: "origin".to_string(),
+            auto_commit: false,
+            overview: true
+        }
+    }
+}
+
+impl HighlighterTheme {
+    /// The name of the theme among the ones bundled with the highlighter
+    pub fn key(self) -> &'static str {
+        match self {
+            HighlighterTheme::SolarizedDark => "Solarized (dark)",
+            HighlighterTheme::Base16Ocean => "base16-ocean.dark",
+            HighlighterTheme::Base16Mocha => "base16-mocha.dark",
+            HighlighterTheme::Base16Eighties => "base16-eighties.dark",
+            HighlighterTheme::InspiredGithub => "InspiredGitHub"
+        }
+    }
+}
+
+impl Config {
+    /// The global config file followed by the one of the vault, which overrides it. Without a
+    /// vault, like before one is chosen, only the global file
+    pub fn paths(vault: Option<&Path>) -> Vec<PathBuf> {
+        let global = dirs::config_dir()
+            .and_then(|dir| PathBuf::try_from(dir).ok())
+            .map(|dir| dir.join("pokisona").join("config.toml"));
+        global
+            .into_iter()
+            .chain(vault.map(|vault| vault.join(".pokisona").join("config.toml")))
+            .collect()
+    }
+
+    /// Loads and merges the config files, missing files are skipped
+    pub fn load(vault: Option<&Path>) -> Result<Self> {
+        let mut config = toml::Table::new();
+        for path in Self::paths(vault) {
+            match fs::read_to_string(&path) {
+                Ok(file) => merge(
+                    &mut config,
+                    toml::from_str(&file)
+                        .map_err(|error| Error::Config(format!("{path}: {}", error.message())))?
+                ),
+                Err(error) if error.kind() == ErrorKind::NotFound => {}
+                Err(error) => return Err(error.into())
+            }
+        }
+
+        let config: Self = toml::Value::Table(config)
+            .try_into()
+            .map_err(|error: toml::de::Error| Error::Config(error.message().to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !SCALE_RANGE.contains(&self.scale) {
+            return Err(Error::Config(
+                "scale must be between 0.25 and 4".to_string()
+            ));
+        }
+
+        if self.editor.line_width <= 0.0 {
+            return Err(Error::Config(
+                "editor.line-width must be positive".to_string()
+            ));
+        }
+
+        if self.editor.padding < 0.0 {
+            return Err(Error::Config(
+                "editor.padding cannot be negative".to_string()
+            ));
+        }
+
+        if self.autosave.delay <= 0.0 {
+            return Err(Error::Config("autosave.delay must be positive".to_string()));
+        }
+
+        if self.theme != "auto"
+            && !theme::BUILTIN.contains(&self.theme.as_str())
+            && !self.themes.contains_key(&self.theme)
+        {
+            return Err(Error::UnknownTheme(self.theme.clone()));
+        }
+
+        if self.extension.is_empty() || self.extension.contains(['.', '/']) {
+            return Err(Error::Config(
+                "extension must be a file extension without the dot".to_string()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Merges tables recursively, values from the overlay win
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::merge;
+
+    #[test]
+    fn test_merge() {
+        let mut base: toml::Table =
+            toml::from_str("scale = 1.5\n[editor]\npadding = 3.0\nline-width = 600.0").unwrap();
+        let overlay = toml::from_str("[editor]\nline-width = 800.0").unwrap();
+        merge(&mut base, overlay);
+        assert_eq!(
+            base,
+            toml::from_str("scale = 1.5\n[editor]\npadding = 3.0\nline-width = 800.0").unwrap()
+        );
+    }
+}
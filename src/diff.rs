@@ -0,0 +1,250 @@
+/// A line of a diff, lines keep their line endings
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str)
+}
+
+/// Line diff based on the longest common subsequence
+pub fn diff<'a>(old: &'a str, new: &'a str) -> Vec<Change<'a>> {
+    bounded_diff(old, new, usize::MAX).unwrap_or_default()
+}
+
+/// Like `diff`, but gives up if more than `limit` pairs of changed lines would have to be
+/// compared, as comparing takes time proportional to that
+pub fn bounded_diff<'a>(old: &'a str, new: &'a str, limit: usize) -> Option<Vec<Change<'a>>> {
+    let old: Vec<_> = old.split_inclusive('\n').collect();
+    let new: Vec<_> = new.split_inclusive('\n').collect();
+    let (prefix, suffix) = common(&old, &new);
+    if (old.len() - prefix - suffix).saturating_mul(new.len() - prefix - suffix) > limit {
+        return None;
+    }
+
+    let matches = matches(&old, &new);
+
+    let mut changes = Vec::new();
+    let mut next = 0;
+    for (line, matched) in old.iter().zip(matches) {
+        match matched {
+            Some(index) => {
+                changes.extend(new[next..index].iter().copied().map(Change::Insert));
+                changes.push(Change::Equal(line));
+                next = index + 1;
+            }
+            None => changes.push(Change::Delete(line))
+        }
+    }
+
+    changes.extend(new[next..].iter().copied().map(Change::Insert));
+    Some(changes)
+}
+
+/// Lengths of the common prefix and suffix of two files, which don't overlap
+fn common(old: &[&str], new: &[&str]) -> (usize, usize) {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    (prefix, suffix)
+}
+
+/// For every line of `old`, the index of the same line in `new` if it's kept
+fn matches(old: &[&str], new: &[&str]) -> Vec<Option<usize>> {
+    // Edits are usually small, so only the part between the common prefix and suffix is compared
+    let (prefix, suffix) = common(old, new);
+    let mut matches: Vec<_> = (0..prefix).map(Some).collect();
+    let middle = lcs(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix]
+    );
+    matches.extend(
+        middle
+            .into_iter()
+            .map(|index| index.map(|index| index + prefix))
+    );
+    matches.extend((new.len() - suffix..new.len()).map(Some));
+    matches
+}
+
+fn lcs(old: &[&str], new: &[&str]) -> Vec<Option<usize>> {
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lengths = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut matches = vec![None; old.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            matches[i] = Some(j);
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    matches
+}
+
+/// Renders the changes as diff hunks with a few lines of context, headed by the line in `old`
+pub fn unified(old: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    let changes = diff(old, new);
+    let visible: Vec<_> = (0..changes.len())
+        .map(|index| {
+            changes[index.saturating_sub(CONTEXT)..(index + CONTEXT + 1).min(changes.len())]
+                .iter()
+                .any(|change| !matches!(change, Change::Equal(_)))
+        })
+        .collect();
+
+    let mut output = String::new();
+    let mut line = 1;
+    for (index, change) in changes.iter().enumerate() {
+        if visible[index] {
+            if index == 0 || !visible[index - 1] {
+                output.push_str(&format!("@@ line {line} @@\n"));
+            }
+
+            let (prefix, text) = match change {
+                Change::Equal(text) => (' ', text),
+                Change::Delete(text) => ('-', text),
+                Change::Insert(text) => ('+', text)
+            };
+            output.push(prefix);
+            output.push_str(text.strip_suffix('\n').unwrap_or(text));
+            output.push('\n');
+        }
+
+        if !matches!(change, Change::Insert(_)) {
+            line += 1;
+        }
+    }
+
+    output
+}
+
+/// Three-way merge of two versions derived from `base`, conflicting changes are surrounded by
+/// git style markers. Returns whether there were conflicts
+pub fn merge(base: &str, ours: &str, theirs: &str) -> (String, bool) {
+    let base: Vec<_> = base.split_inclusive('\n').collect();
+    let ours: Vec<_> = ours.split_inclusive('\n').collect();
+    let theirs: Vec<_> = theirs.split_inclusive('\n').collect();
+    let ours_matches = matches(&base, &ours);
+    let theirs_matches = matches(&base, &theirs);
+
+    let mut merged = String::new();
+    let mut conflicts = false;
+    let (mut base_start, mut ours_start, mut theirs_start) = (0, 0, 0);
+    loop {
+        // The next line of the base that both versions kept
+        let stable = (base_start..base.len())
+            .find_map(|index| Some((index, ours_matches[index]?, theirs_matches[index]?)));
+        let (base_end, ours_end, theirs_end) =
+            stable.unwrap_or((base.len(), ours.len(), theirs.len()));
+
+        let base_chunk = &base[base_start..base_end];
+        let ours_chunk = &ours[ours_start..ours_end];
+        let theirs_chunk = &theirs[theirs_start..theirs_end];
+        if ours_chunk == base_chunk || ours_chunk == theirs_chunk {
+            merged.extend(theirs_chunk.iter().copied());
+        } else if theirs_chunk == base_chunk {
+            merged.extend(ours_chunk.iter().copied());
+        } else {
+            conflicts = true;
+            for (marker, chunk) in [
+                ("<<<<<<< buffer\n", ours_chunk),
+                ("||||||| original\n", base_chunk),
+                ("=======\n", theirs_chunk)
+            ] {
+                merged.push_str(marker);
+                merged.extend(chunk.iter().copied());
+                if !merged.ends_with('\n') {
+                    merged.push('\n');
+                }
+            }
+
+            merged.push_str(">>>>>>> disk\n");
+        }
+
+        let Some((base_end, ours_end, theirs_end)) = stable else {
+            break;
+        };
+
+        merged.push_str(base[base_end]);
+        (base_start, ours_start, theirs_start) = (base_end + 1, ours_end + 1, theirs_end + 1);
+    }
+
+    (merged, conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::{Change, bounded_diff, diff, merge, unified};
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(
+            diff("a\nb\nc\n", "a\nc\nd\n"),
+            vec![
+                Change::Equal("a\n"),
+                Change::Delete("b\n"),
+                Change::Equal("c\n"),
+                Change::Insert("d\n")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bounded_diff() {
+        let old = "title\na\nb\nc\nend\n";
+        let new = "title\nx\ny\nend\n";
+        assert_eq!(bounded_diff(old, new, 5), None);
+        assert_eq!(bounded_diff(old, new, 6), Some(diff(old, new)));
+    }
+
+    #[test]
+    fn test_unified() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        assert_eq!(
+            unified(old, &old.replace("9\n", "nine\n")),
+            "@@ line 6 @@\n 6\n 7\n 8\n-9\n+nine\n 10\n"
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = "title\none\ntwo\nthree\n";
+        assert_eq!(
+            merge(base, "title\nONE\ntwo\nthree\n", "title\none\ntwo\nTHREE\n"),
+            ("title\nONE\ntwo\nTHREE\n".to_string(), false)
+        );
+        assert_eq!(
+            merge(base, "title\nuno\ntwo\nthree\n", "title\neins\ntwo\nthree\n"),
+            (
+                "title\n<<<<<<< buffer\nuno\n||||||| original\none\n=======\neins\n>>>>>>> disk\ntwo\nthree\n"
+                    .to_string(),
+                true
+            )
+        );
+    }
+}
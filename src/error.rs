@@ -1,6 +1,6 @@
 use std::{fmt::Display, io::ErrorKind};
 
-use crate::command::CommandParseErr;
+use crate::{PathBuf, command::CommandParseErr};
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -8,10 +8,51 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub enum Error {
     Command(CommandParseErr),
     IO(ErrorKind),
+    Config(String),
     CannotQuitWithUnsavedBuffer,
+    CannotCloseUnsavedBuffer,
+    CannotRemoveUnsavedBuffer,
+    CannotCloseLastPane,
+    CannotCloseLastTab,
+    ReadOnlyBuffer,
     WriteParentDirectoryDoesntExist,
     MoveParentDirectoryDoesntExist,
-    NoPathSet
+    NoPathSet,
+    NoSelection,
+    UnboundKey(String),
+    ShellCommandFailed {
+        code: Option<i32>,
+        stderr: String
+    },
+    ShellOutputNotUtf8,
+    InvalidTrashInfo,
+    NotInTrash,
+    RestoreTargetExists,
+    RenameTargetExists(PathBuf),
+    NoDataDir,
+    InvalidVaultRegistry(String),
+    InvalidVaultName(String),
+    VaultDoesntExist(String),
+    VaultAlreadyExists(String),
+    NotInVault(String),
+    InvalidRequest(String),
+    UnknownTheme(String),
+    InvalidState(String),
+    InvalidSession(String),
+    Watch(String),
+    ChangedOnDisk(PathBuf),
+    NotChangedOnDisk,
+    /// The buffers that couldn't be autosaved by title
+    AutosaveFailed(Vec<(String, Error)>),
+    MergeConflicts,
+    NoHistory,
+    InvalidHistory(String),
+    NoTags,
+    NotGitRepository,
+    NothingToCommit,
+    GitConflict(String),
+    GitRejected(String),
+    GitFailed(String)
 }
 
 impl Display for Error {
@@ -21,19 +62,96 @@ impl Display for Error {
                 CommandParseErr::TooManyArgs => f.write_str("Too many arguments"),
                 CommandParseErr::NotEnoughArgs => f.write_str("Not enough arguments"),
                 CommandParseErr::Unknown => f.write_str("Unknown command"),
-                CommandParseErr::CannotParse => f.write_str("Cannot parse command")
+                CommandParseErr::CannotParse => f.write_str("Cannot parse command"),
+                CommandParseErr::InvalidAliasName => {
+                    f.write_str("Alias names can only contain letters and dashes")
+                }
+                CommandParseErr::AliasShadowsCommand => {
+                    f.write_str("Cannot define an alias with the name of a command")
+                }
+                CommandParseErr::RecursiveAlias => f.write_str("Alias expands recursively")
             },
             Error::IO(error_kind) => write!(f, "IO error: {error_kind}"),
+            Error::Config(error) => write!(f, "Invalid config: {error}"),
             Error::CannotQuitWithUnsavedBuffer => {
                 f.write_str("Cannot quit with unsaved buffer, use q! to override")
             }
+            Error::CannotCloseUnsavedBuffer => {
+                f.write_str("Cannot close unsaved buffer, use bc! to override")
+            }
+            Error::CannotRemoveUnsavedBuffer => {
+                f.write_str("Cannot remove the file of an unsaved buffer, add ! to override")
+            }
+            Error::CannotCloseLastPane => f.write_str("Cannot close the last pane"),
+            Error::CannotCloseLastTab => f.write_str("Cannot close the last tab"),
+            Error::ReadOnlyBuffer => f.write_str("Buffer is read-only"),
             Error::WriteParentDirectoryDoesntExist => {
                 f.write_str("Parent directory doesn't exist, use w! to create")
             }
             Error::MoveParentDirectoryDoesntExist => {
                 f.write_str("Parent directory doesn't exist, use mv! to create")
             }
-            Error::NoPathSet => f.write_str("Cannot write with no path set")
+            Error::NoPathSet => f.write_str("Cannot write with no path set"),
+            Error::UnboundKey(key) => write!(f, "Unbound key {key}"),
+            Error::NoSelection => f.write_str("Nothing is selected"),
+            Error::ShellCommandFailed { code, stderr } => {
+                match code {
+                    Some(code) => write!(f, "Command exited with code {code}")?,
+                    None => f.write_str("Command was terminated by a signal")?
+                }
+
+                match stderr.lines().next() {
+                    Some(line) => write!(f, ": {line}"),
+                    None => Ok(())
+                }
+            }
+            Error::ShellOutputNotUtf8 => f.write_str("Command output is not valid UTF-8"),
+            Error::InvalidTrashInfo => f.write_str("Invalid trash metadata"),
+            Error::NotInTrash => f.write_str("No such file in the trash"),
+            Error::RestoreTargetExists => {
+                f.write_str("Cannot restore, a file already exists at the original path")
+            }
+            Error::RenameTargetExists(path) => write!(f, "Cannot rename, {path} already exists"),
+            Error::NoDataDir => f.write_str("Cannot determine data dir"),
+            Error::InvalidVaultRegistry(error) => write!(f, "Invalid vault registry: {error}"),
+            Error::InvalidVaultName(name) => write!(f, "Invalid vault name {name:?}"),
+            Error::VaultDoesntExist(name) => write!(f, "Vault {name} doesn't exist"),
+            Error::VaultAlreadyExists(name) => write!(f, "Vault {name} already exists"),
+            Error::NotInVault(path) => write!(f, "No parent folder of {path} is a vault"),
+            Error::InvalidRequest(error) => write!(f, "Invalid remote request: {error}"),
+            Error::UnknownTheme(name) => write!(f, "Unknown theme {name}"),
+            Error::InvalidState(error) => write!(f, "Invalid .pokisona/state.toml: {error}"),
+            Error::InvalidSession(error) => write!(f, "Invalid .pokisona/session.json: {error}"),
+            Error::Watch(error) => write!(f, "Cannot watch files for changes: {error}"),
+            Error::ChangedOnDisk(path) => write!(
+                f,
+                "{path} changed on disk, use diff to compare and merge, reload or w! to resolve"
+            ),
+            Error::NotChangedOnDisk => f.write_str("File didn't change on disk"),
+            Error::AutosaveFailed(errors) => {
+                f.write_str("Autosave failed")?;
+                for (index, (title, error)) in errors.iter().enumerate() {
+                    let separator = if index == 0 { ": " } else { "; " };
+                    write!(f, "{separator}{title}: {error}")?;
+                }
+
+                Ok(())
+            }
+            Error::MergeConflicts => {
+                f.write_str("Merged with conflicts, resolve the marked sections")
+            }
+            Error::NoHistory => {
+                f.write_str("No snapshots of this file yet, they are taken on every write")
+            }
+            Error::InvalidHistory(error) => write!(f, "Invalid history index: {error}"),
+            Error::NoTags => f.write_str("No tags in the vault, add #tags or frontmatter tags"),
+            Error::NotGitRepository => f.write_str("The vault isn't a git repository"),
+            Error::NothingToCommit => f.write_str("Nothing to commit"),
+            Error::GitConflict(conflict) => {
+                write!(f, "{conflict}, resolve the conflict and commit")
+            }
+            Error::GitRejected(error) => write!(f, "Push rejected, pull first: {error}"),
+            Error::GitFailed(error) => write!(f, "Git failed: {error}")
         }
     }
 }
@@ -0,0 +1,315 @@
+use std::collections::HashSet;
+
+use iced::{
+    Length, padding,
+    widget::{column, container, mouse_area, row, scrollable, text}
+};
+use lucide_icons::Icon;
+
+use crate::{Element, Path, PathBuf, Pokisona, error::Result, mode::Mode, session, theme::Palette};
+
+/// The folders and files of the vault shown as a tree next to the editor
+#[derive(Default)]
+pub struct Explorer {
+    pub visible: bool,
+    /// Folders whose content is shown
+    expanded: HashSet<PathBuf>,
+    /// Shown entries in order, read again whenever the vault changes
+    entries: Vec<Entry>,
+    /// The entry the keys act on, kept by path so it survives refreshes
+    selected: Option<PathBuf>,
+    /// The entry the mouse was pressed on, moved to where it's released
+    dragged: Option<PathBuf>
+}
+
+struct Entry {
+    path: PathBuf,
+    depth: usize,
+    folder: bool
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Up,
+    Down,
+    /// Opens the selected file or expands the selected folder
+    Expand,
+    /// Collapses the selected folder or selects the parent folder
+    Collapse,
+    Create,
+    Rename,
+    Trash,
+    Press(PathBuf),
+    /// The mouse was released over an entry, the root if the path is empty
+    Release(PathBuf)
+}
+
+/// What the editor should do in response to the explorer
+pub enum Action {
+    Open(PathBuf),
+    /// Asks for the name of a new note in a folder
+    Create(PathBuf),
+    Rename(PathBuf),
+    Trash(PathBuf),
+    Move(PathBuf, PathBuf)
+}
+
+impl Message {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Message::Up => "Select the entry above",
+            Message::Down => "Select the entry below",
+            Message::Expand => "Open the file or expand the folder",
+            Message::Collapse => "Collapse the folder or select the parent folder",
+            Message::Create => "Create a note in the folder",
+            Message::Rename => "Rename the entry, updating the links to it",
+            Message::Trash => "Move the entry to the vault trash",
+            Message::Press(_) | Message::Release(_) => "Drag the entry"
+        }
+    }
+}
+
+impl Explorer {
+    const WIDTH: f32 = 250.0;
+    const INDENT: f32 = 16.0;
+    const SPACING: f32 = 6.0;
+
+    /// Reads the vault tree again, hidden entries like `.pokisona` are skipped
+    pub fn refresh(&mut self) -> Result<()> {
+        self.expanded.retain(|folder| folder.is_dir());
+        self.entries.clear();
+        self.read(Path::new(""), 0)
+    }
+
+    fn read(&mut self, folder: &Path, depth: usize) -> Result<()> {
+        let dir = if folder.as_str().is_empty() {
+            Path::new(".")
+        } else {
+            folder
+        };
+
+        let mut children = Vec::new();
+        for entry in dir.read_dir_utf8()? {
+            let entry = entry?;
+            if !entry.file_name().starts_with('.') {
+                children.push((folder.join(entry.file_name()), entry.file_type()?.is_dir()));
+            }
+        }
+
+        children.sort_by_cached_key(|(path, folder)| (!folder, path.as_str().to_lowercase()));
+        for (path, folder) in children {
+            let expanded = folder && self.expanded.contains(&path);
+            self.entries.push(Entry {
+                path: path.clone(),
+                depth,
+                folder
+            });
+            if expanded {
+                self.read(&path, depth + 1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// What's kept of the explorer for the next session
+    pub fn state(&self) -> session::Explorer {
+        session::Explorer {
+            visible: self.visible,
+            expanded: self.expanded.iter().cloned().collect(),
+            selected: self.selected.clone()
+        }
+    }
+
+    pub fn restore(&mut self, state: session::Explorer) -> Result<()> {
+        self.visible = state.visible;
+        self.expanded = state.expanded.into_iter().collect();
+        self.selected = state.selected;
+        if self.visible {
+            self.refresh()?;
+        }
+
+        Ok(())
+    }
+
+    /// Expands the folders containing a file and selects it
+    pub fn reveal(&mut self, path: &Path) -> Result<()> {
+        self.expanded.extend(
+            path.ancestors()
+                .skip(1)
+                .filter(|folder| !folder.as_str().is_empty())
+                .map(Path::to_path_buf)
+        );
+        self.selected = Some(path.to_path_buf());
+        self.refresh()
+    }
+
+    fn position(&self) -> Option<usize> {
+        let selected = self.selected.as_ref()?;
+        self.entries
+            .iter()
+            .position(|entry| entry.path == *selected)
+    }
+
+    fn entry(&self) -> Option<&Entry> {
+        self.entries.get(self.position()?)
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = self.entries.get(index).map(|entry| entry.path.clone());
+    }
+
+    /// Opens a file or toggles a folder
+    fn activate(&mut self, path: PathBuf, folder: bool) -> Result<Option<Action>> {
+        if !folder {
+            return Ok(Some(Action::Open(path)));
+        }
+
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+
+        self.refresh()?;
+        Ok(None)
+    }
+
+    pub fn update(&mut self, message: Message) -> Result<Option<Action>> {
+        let last = self.entries.len().saturating_sub(1);
+        match message {
+            Message::Up => self.select(
+                self.position()
+                    .map_or(last, |index| index.saturating_sub(1))
+            ),
+            Message::Down => self.select(self.position().map_or(0, |index| (index + 1).min(last))),
+            Message::Expand => {
+                if let Some(entry) = self.entry() {
+                    let (path, folder) = (entry.path.clone(), entry.folder);
+                    return self.activate(path, folder);
+                }
+            }
+            Message::Collapse => {
+                let Some(entry) = self.entry() else {
+                    return Ok(None);
+                };
+
+                if entry.folder && self.expanded.contains(&entry.path) {
+                    let path = entry.path.clone();
+                    return self.activate(path, true);
+                }
+
+                if let Some(parent) = entry
+                    .path
+                    .parent()
+                    .filter(|parent| !parent.as_str().is_empty())
+                {
+                    self.selected = Some(parent.to_path_buf());
+                }
+            }
+            Message::Create => {
+                let folder = match self.entry() {
+                    Some(entry) if entry.folder => entry.path.clone(),
+                    Some(entry) => entry.path.parent().unwrap_or(Path::new("")).to_path_buf(),
+                    None => PathBuf::new()
+                };
+
+                return Ok(Some(Action::Create(folder)));
+            }
+            Message::Rename => return Ok(self.selected.clone().map(Action::Rename)),
+            Message::Trash => return Ok(self.selected.clone().map(Action::Trash)),
+            Message::Press(path) => {
+                self.selected = Some(path.clone());
+                self.dragged = Some(path);
+            }
+            Message::Release(target) => {
+                let Some(dragged) = self.dragged.take() else {
+                    return Ok(None);
+                };
+
+                // Releasing where the mouse was pressed is a click
+                if dragged == target {
+                    let folder = self.entry().is_some_and(|entry| entry.folder);
+                    return self.activate(target, folder);
+                }
+
+                let folder = if target.is_dir() || target.as_str().is_empty() {
+                    target
+                } else {
+                    target.parent().unwrap_or(Path::new("")).to_path_buf()
+                };
+
+                // Moving a folder into itself, or a file into the folder it's already in
+                if folder.starts_with(&dragged) || dragged.parent() == Some(folder.as_path()) {
+                    return Ok(None);
+                }
+
+                return Ok(Some(Action::Move(dragged, folder)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Shows the tree with the open file highlighted, the selection is only shown while the
+    /// explorer is focused
+    pub fn view<'a>(
+        &'a self,
+        palette: &'a Palette,
+        current: Option<&Path>,
+        focused: bool
+    ) -> Element<'a, Message> {
+        let entries = column(self.entries.iter().map(|entry| {
+            let icon = match (entry.folder, self.expanded.contains(&entry.path)) {
+                (true, true) => Icon::FolderOpen,
+                (true, false) => Icon::Folder,
+                (false, _) => Icon::FileText
+            };
+            let is_current = current == Some(entry.path.as_path());
+            let is_selected = focused && self.selected.as_ref() == Some(&entry.path);
+            let color = if is_current {
+                palette.text
+            } else {
+                palette.subtext
+            };
+            let background = if is_selected {
+                Some(palette.selection(Mode::Panel))
+            } else if is_current {
+                Some(palette.background)
+            } else {
+                None
+            };
+
+            let name = entry.path.file_name().unwrap_or_default();
+            let entry_row = container(
+                row![Pokisona::icon(icon).color(color), text(name).color(color)]
+                    .spacing(Self::SPACING)
+            )
+            .width(Length::Fill)
+            .padding(padding::left(entry.depth as f32 * Self::INDENT).right(Self::SPACING))
+            .style(move |_| container::Style {
+                background: background.map(Into::into),
+                ..Default::default()
+            });
+            mouse_area(entry_row)
+                .on_press(Message::Press(entry.path.clone()))
+                .on_release(Message::Release(entry.path.clone()))
+                .into()
+        }));
+
+        // Dropping below the entries moves to the root of the vault
+        let tree = mouse_area(
+            container(scrollable(entries))
+                .width(Length::Fill)
+                .height(Length::Fill)
+        )
+        .on_release(Message::Release(PathBuf::new()));
+        container(tree)
+            .width(Self::WIDTH)
+            .height(Length::Fill)
+            .padding(padding::top(Self::SPACING))
+            .style(|_| container::Style {
+                background: Some(palette.bar.into()),
+                ..Default::default()
+            })
+            .into()
+    }
+}
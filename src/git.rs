@@ -0,0 +1,272 @@
+use std::{io::ErrorKind, process::Stdio};
+
+use smol::process;
+
+use crate::{
+    Path, PathBuf,
+    diff::{self, Change},
+    error::{Error, Result}
+};
+
+/// How a line of the buffer differs from the committed version
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineChange {
+    Added,
+    Modified,
+    /// Lines were removed right before this one
+    Removed
+}
+
+/// Runs of lines of a buffer with the same change against the committed version, shown in the
+/// overview bar
+pub type Overview = Vec<(Option<LineChange>, usize)>;
+
+/// Pairs of changed lines compared at most for the overview bar, so that typing in a large file
+/// that differs a lot from the committed version stays responsive
+const OVERVIEW_LIMIT: usize = 1_000_000;
+
+/// Runs git in the repository at `root`
+async fn run(root: &Path, args: &[&str]) -> Result<String> {
+    let output = process::Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !output.status.success() {
+        return Err(error(&stdout, &String::from_utf8_lossy(&output.stderr)));
+    }
+
+    Ok(stdout)
+}
+
+/// Turns the output of a failed git command into an error
+fn error(stdout: &str, stderr: &str) -> Error {
+    let lines = || stdout.lines().chain(stderr.lines());
+    if let Some(line) = lines().find(|line| line.starts_with("CONFLICT")) {
+        return Error::GitConflict(line.to_string());
+    }
+
+    if stderr.contains("not a git repository") {
+        return Error::NotGitRepository;
+    }
+
+    if stdout.contains("nothing to commit") || stdout.contains("no changes added to commit") {
+        return Error::NothingToCommit;
+    }
+
+    if let Some(line) = stderr.lines().find(|line| line.contains("[rejected]")) {
+        return Error::GitRejected(line.trim().to_string());
+    }
+
+    let message = lines()
+        .map(|line| line.trim_start_matches("fatal: ").trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or("unknown error");
+    Error::GitFailed(message.to_string())
+}
+
+/// The checked out branch, none outside of a repository or with a detached HEAD
+pub async fn branch(root: PathBuf) -> Result<Option<String>> {
+    match run(&root, &["symbolic-ref", "--short", "HEAD"]).await {
+        Ok(branch) => Ok(Some(branch.trim().to_string())),
+        Err(Error::NotGitRepository | Error::GitFailed(_) | Error::IO(ErrorKind::NotFound)) => {
+            Ok(None)
+        }
+        Err(error) => Err(error)
+    }
+}
+
+/// The content of a file at HEAD, none if it isn't committed
+pub async fn head(root: PathBuf, path: PathBuf) -> Result<Option<String>> {
+    match run(&root, &["show", &format!("HEAD:./{path}")]).await {
+        Ok(text) => Ok(Some(text)),
+        Err(Error::NotGitRepository | Error::GitFailed(_) | Error::IO(ErrorKind::NotFound)) => {
+            Ok(None)
+        }
+        Err(error) => Err(error)
+    }
+}
+
+/// Commits everything, or only the given file
+pub async fn commit(root: PathBuf, message: String, path: Option<PathBuf>) -> Result<String> {
+    let Some(path) = path else {
+        run(&root, &["add", "--all"]).await?;
+        return run(&root, &["commit", "--message", &message]).await;
+    };
+
+    run(&root, &["add", "--", path.as_str()]).await?;
+    match run(
+        &root,
+        &["commit", "--message", &message, "--", path.as_str()]
+    )
+    .await
+    {
+        // Writing a file doesn't have to change it
+        Err(Error::NothingToCommit) => Ok(String::new()),
+        result => result
+    }
+}
+
+/// Merges the branch of the remote with the same name as the checked out one
+pub async fn pull(root: PathBuf, remote: String) -> Result<String> {
+    let branch = branch(root.clone()).await?.ok_or(Error::NotGitRepository)?;
+    run(
+        &root,
+        &["pull", "--no-rebase", "--no-edit", &remote, &branch]
+    )
+    .await
+}
+
+pub async fn push(root: PathBuf, remote: String) -> Result<String> {
+    run(&root, &["push", &remote, "HEAD"]).await
+}
+
+/// Where the buffer differs from the committed version, none if they differ too much to compare
+pub fn overview(head: &str, text: &str) -> Option<Overview> {
+    let mut lines = Vec::new();
+    let mut deleted = 0;
+    for change in diff::bounded_diff(head, text, OVERVIEW_LIMIT)? {
+        match change {
+            Change::Delete(_) => deleted += 1,
+            Change::Insert(_) if deleted > 0 => {
+                deleted -= 1;
+                lines.push(Some(LineChange::Modified));
+            }
+            Change::Insert(_) => lines.push(Some(LineChange::Added)),
+            Change::Equal(_) if deleted > 0 => {
+                deleted = 0;
+                lines.push(Some(LineChange::Removed));
+            }
+            Change::Equal(_) => lines.push(None)
+        }
+    }
+
+    if deleted > 0 {
+        lines.push(Some(LineChange::Removed));
+    }
+
+    let mut runs: Vec<(Option<LineChange>, usize)> = Vec::new();
+    for line in lines {
+        match runs.last_mut() {
+            Some((change, count)) if *change == line => *count += 1,
+            _ => runs.push((line, 1))
+        }
+    }
+
+    Some(runs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use crate::{
+        Path, PathBuf,
+        error::Error,
+        git::{LineChange, commit, error, overview, pull, push}
+    };
+
+    fn git(root: &Path, args: &[&str]) {
+        let status = process::Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(args)
+            .stdout(process::Stdio::null())
+            .stderr(process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[test]
+    fn test_overview() {
+        assert_eq!(
+            overview("a\nb\nc\nd\n", "a\nB\nc\nnew\n"),
+            Some(vec![
+                (None, 1),
+                (Some(LineChange::Modified), 1),
+                (None, 1),
+                (Some(LineChange::Modified), 1)
+            ])
+        );
+        assert_eq!(
+            overview("a\nb\nc\n", "a\nc\nd\n"),
+            Some(vec![
+                (None, 1),
+                (Some(LineChange::Removed), 1),
+                (Some(LineChange::Added), 1)
+            ])
+        );
+
+        let lines = |prefix: &str| (0..2000).map(|line| format!("{prefix}{line}\n")).collect();
+        let (head, text): (String, String) = (lines("old"), lines("new"));
+        assert_eq!(overview(&head, &text), None);
+    }
+
+    #[test]
+    fn test_pull_push() {
+        let root = PathBuf::try_from(env::temp_dir())
+            .unwrap()
+            .join(format!("pokisona-git-{}", process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        git(
+            &root,
+            &["init", "--bare", "--initial-branch=main", "remote.git"]
+        );
+        let (a, b) = (root.join("a"), root.join("b"));
+        for clone in [&a, &b] {
+            git(&root, &["clone", "remote.git", clone.as_str()]);
+            git(clone, &["symbolic-ref", "HEAD", "refs/heads/main"]);
+            git(clone, &["config", "user.name", "Pokisona"]);
+            git(clone, &["config", "user.email", "pokisona@example.com"]);
+        }
+
+        let origin = || "origin".to_string();
+        fs::write(a.join("idea.md"), "first\n").unwrap();
+        smol::block_on(commit(a.clone(), "Add idea".to_string(), None)).unwrap();
+        smol::block_on(push(a.clone(), origin())).unwrap();
+
+        smol::block_on(pull(b.clone(), origin())).unwrap();
+        assert_eq!(fs::read_to_string(b.join("idea.md")).unwrap(), "first\n");
+        fs::write(b.join("idea.md"), "second\n").unwrap();
+        smol::block_on(commit(b.clone(), "Change idea".to_string(), None)).unwrap();
+        smol::block_on(push(b.clone(), origin())).unwrap();
+
+        smol::block_on(pull(a.clone(), origin())).unwrap();
+        assert_eq!(fs::read_to_string(a.join("idea.md")).unwrap(), "second\n");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_error() {
+        assert!(matches!(
+            error(
+                "Auto-merging idea.md\nCONFLICT (content): Merge conflict in idea.md\n",
+                ""
+            ),
+            Error::GitConflict(line) if line == "CONFLICT (content): Merge conflict in idea.md"
+        ));
+        assert!(matches!(
+            error(
+                "",
+                " ! [rejected]        HEAD -> main (fetch first)\nerror: failed to push some refs\n"
+            ),
+            Error::GitRejected(_)
+        ));
+        assert!(matches!(
+            error(
+                "",
+                "fatal: not a git repository (or any of the parent directories): .git\n"
+            ),
+            Error::NotGitRepository
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use std::fmt::Write;
+
+use itertools::Itertools;
+use strum::IntoEnumIterator;
+
+use crate::{
+    command::{self, COMMANDS, CommandInfo, CommandParseErr, CommandRegistry},
+    mode::{self, Mode, Prefix}
+};
+
+/// Generates the help page, either for every command and keybinding or for a single command
+pub fn generate(
+    command: Option<&str>,
+    registry: &CommandRegistry
+) -> Result<String, CommandParseErr> {
+    let mut help = String::new();
+    if let Some(name) = command {
+        let name = name.trim_end_matches('!');
+        match (command::lookup(name), registry.alias(name)) {
+            (Some(info), _) => write_command(&mut help, info),
+            (None, Some(alias)) => write_alias(&mut help, name, alias),
+            (None, None) => return Err(CommandParseErr::Unknown)
+        }
+
+        return Ok(help);
+    }
+
+    help.push_str("# Commands\n\n");
+    for info in COMMANDS {
+        write_command(&mut help, info);
+    }
+
+    let aliases = registry.aliases().sorted().collect::<Vec<_>>();
+    if !aliases.is_empty() {
+        help.push_str("# Aliases\n\n");
+        for (name, alias) in aliases {
+            write_alias(&mut help, name, alias);
+        }
+    }
+
+    help.push_str("# Keybindings\n");
+    for mode in Mode::iter() {
+        write_keymap(&mut help, mode.as_ref(), mode.keymap());
+    }
+
+    for prefix in Prefix::iter() {
+        let title = format!("After {}", prefix.as_ref());
+        write_keymap(&mut help, &title, mode::keymap(prefix.bindings()));
+    }
+
+    Ok(help)
+}
+
+fn write_keymap(help: &mut String, title: &str, keymap: Vec<(String, String)>) {
+    let _ = write!(help, "\n## {title}\n\n| Key | Action |\n|---|---|\n");
+    for (key, action) in keymap {
+        let _ = writeln!(help, "| `{key}` | {action} |");
+    }
+}
+
+fn write_alias(help: &mut String, name: &str, alias: &str) {
+    let _ = writeln!(help, "## `:{name}`\n\nAlias for `:{alias}`\n");
+}
+
+fn write_command(help: &mut String, info: &CommandInfo) {
+    let _ = writeln!(help, "## `:{}` {}\n", info.name, info.args);
+    if !info.aliases.is_empty() {
+        let aliases = info
+            .aliases
+            .iter()
+            .map(|alias| format!("`{alias}`"))
+            .join(", ");
+        let _ = writeln!(help, "Aliases: {aliases}\n");
+    }
+
+    let _ = writeln!(help, "{}\n", info.description);
+    if let Some(force) = info.force {
+        let _ = writeln!(help, "`:{}!`: {force}\n", info.name);
+    }
+}
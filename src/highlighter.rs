@@ -0,0 +1,275 @@
+use std::{ops::Range, str::FromStr, sync::LazyLock, vec};
+
+use iced::{
+    Color, Font,
+    advanced::text::{self, highlighter::Format},
+    font
+};
+use two_face::re_exports::syntect::{
+    highlighting::{self, FontStyle, ScopeSelectors, StyleModifier, Theme, ThemeItem, ThemeSet},
+    parsing::{ParseState, ScopeStack, ScopeStackOp, SyntaxReference, SyntaxSet}
+};
+
+use crate::{
+    markdown::Markdown,
+    theme::{Syntax, SyntaxColors}
+};
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(two_face::syntax::extra_no_newlines);
+static PRESETS: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+/// Syntax highlighting with the `#tags` of notes colored on top
+pub struct Highlighter {
+    syntax: &'static SyntaxReference,
+    theme: Theme,
+    tag: Option<Color>,
+    /// The state each highlighted line starts in, one more than the highlighted lines
+    lines: Vec<Line>
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Settings {
+    pub syntax: Syntax,
+    /// Picks the syntax, like a file extension
+    pub token: String,
+    /// The color of tags, none for files that aren't notes
+    pub tag: Option<Color>
+}
+
+pub enum Highlight {
+    Syntax(StyleModifier),
+    Tag(Color)
+}
+
+#[derive(Clone)]
+struct Line {
+    parse: ParseState,
+    scopes: ScopeStack,
+    block: Block
+}
+
+/// Blocks spanning several lines in which `#` starts no tag
+#[derive(Clone, Copy, PartialEq)]
+enum Block {
+    Text,
+    Frontmatter,
+    Fenced
+}
+
+impl Highlight {
+    pub fn to_format(&self) -> Format<Font> {
+        match self {
+            Highlight::Syntax(style) => Format {
+                color: style.foreground.map(|color| {
+                    Color::from_rgba8(color.r, color.g, color.b, color.a as f32 / 255.0)
+                }),
+                font: style.font_style.and_then(|style| {
+                    let bold = style.contains(FontStyle::BOLD);
+                    let italic = style.contains(FontStyle::ITALIC);
+                    (bold || italic).then_some(Font {
+                        weight: if bold {
+                            font::Weight::Bold
+                        } else {
+                            font::Weight::Normal
+                        },
+                        style: if italic {
+                            font::Style::Italic
+                        } else {
+                            font::Style::Normal
+                        },
+                        ..Font::MONOSPACE
+                    })
+                })
+            },
+            Highlight::Tag(color) => Format {
+                color: Some(*color),
+                font: None
+            }
+        }
+    }
+}
+
+impl text::Highlighter for Highlighter {
+    type Settings = Settings;
+    type Highlight = Highlight;
+    type Iterator<'a> = vec::IntoIter<(Range<usize>, Highlight)>;
+
+    fn new(settings: &Settings) -> Self {
+        let syntax = SYNTAXES
+            .find_syntax_by_token(&settings.token)
+            .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text());
+
+        Self {
+            syntax,
+            theme: theme(&settings.syntax),
+            tag: settings.tag,
+            lines: vec![Line::new(syntax)]
+        }
+    }
+
+    fn update(&mut self, settings: &Settings) {
+        *self = Self::new(settings);
+    }
+
+    fn change_line(&mut self, line: usize) {
+        self.lines.truncate(line + 1);
+    }
+
+    fn highlight_line(&mut self, line: &str) -> Self::Iterator<'_> {
+        let mut state = self
+            .lines
+            .last()
+            .cloned()
+            .unwrap_or_else(|| Line::new(self.syntax));
+        let highlighter = highlighting::Highlighter::new(&self.theme);
+        let ops = state.parse.parse_line(line, &SYNTAXES).unwrap_or_default();
+
+        // Every range between two scope changes is styled by the scopes it's in
+        let mut highlights = Vec::new();
+        let mut start = 0;
+        for (end, op) in ops.into_iter().chain([(line.len(), ScopeStackOp::Noop)]) {
+            if end > start {
+                let style = highlighter.style_mod_for_stack(&state.scopes.scopes);
+                highlights.push((start..end, Highlight::Syntax(style)));
+                start = end;
+            }
+            let _ = state.scopes.apply(&op);
+        }
+
+        let block = state.block;
+        let is_first = self.lines.len() == 1;
+        let next = match (block, line.trim_end()) {
+            (Block::Text, "---") if is_first => Block::Frontmatter,
+            (Block::Frontmatter, "---" | "...") => Block::Text,
+            (Block::Text, fence) if fence.trim_start().starts_with("```") => Block::Fenced,
+            (Block::Fenced, fence) if fence.trim_start().starts_with("```") => Block::Text,
+            (block, _) => block
+        };
+        state.block = next;
+        self.lines.push(state);
+
+        // Later highlights are drawn over the earlier ones
+        if let Some(color) = self.tag
+            && block == Block::Text
+            && next == Block::Text
+        {
+            highlights.extend(
+                Markdown::parse(line)
+                    .tags()
+                    .into_iter()
+                    .map(|span| (span.start - 1..span.end, Highlight::Tag(color)))
+            );
+        }
+
+        highlights.into_iter()
+    }
+
+    fn current_line(&self) -> usize {
+        self.lines.len() - 1
+    }
+}
+
+impl Line {
+    fn new(syntax: &SyntaxReference) -> Self {
+        Self {
+            parse: ParseState::new(syntax),
+            scopes: ScopeStack::new(),
+            block: Block::Text
+        }
+    }
+}
+
+/// The scopes colored by each token color of a palette
+fn scopes(colors: &SyntaxColors) -> [(&'static str, Option<Color>); 9] {
+    [
+        ("comment", colors.comment),
+        ("string", colors.string),
+        ("constant", colors.constant),
+        ("keyword, storage", colors.keyword),
+        ("entity.name.function, support.function", colors.function),
+        (
+            "entity.name.type, entity.name.class, storage.type, support.type, support.class",
+            colors.kind
+        ),
+        ("markup.heading, entity.name.section", colors.heading),
+        ("markup.underline.link, string.other.link", colors.link),
+        ("markup.raw", colors.code)
+    ]
+}
+
+fn theme(syntax: &Syntax) -> Theme {
+    let colors = match syntax {
+        Syntax::Preset(preset) => return PRESETS.themes[preset.key()].clone(),
+        Syntax::Colors(colors) => colors
+    };
+
+    let style = |color: Option<Color>, font_style| StyleModifier {
+        foreground: color.map(|color| {
+            let [r, g, b, a] = color.into_rgba8();
+            highlighting::Color { r, g, b, a }
+        }),
+        background: None,
+        font_style
+    };
+    let item = |scope: &str, style| ThemeItem {
+        scope: ScopeSelectors::from_str(scope).expect("the scope selectors are valid"),
+        style
+    };
+
+    let fonts = [
+        item("markup.bold", style(None, Some(FontStyle::BOLD))),
+        item("markup.italic", style(None, Some(FontStyle::ITALIC)))
+    ];
+    let colors = scopes(colors)
+        .into_iter()
+        .filter(|(_, color)| color.is_some())
+        .map(|(scope, color)| item(scope, style(color, None)));
+
+    Theme {
+        scopes: fonts.into_iter().chain(colors).collect(),
+        ..Theme::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::{Color, advanced::text::Highlighter as _};
+
+    use crate::{
+        config::HighlighterTheme,
+        highlighter::{Highlighter, Settings},
+        theme::{Syntax, SyntaxColors}
+    };
+
+    #[test]
+    fn test_syntax_colors() {
+        let comment = Color::from_rgb8(0x12, 0x34, 0x56);
+        let mut highlighter = Highlighter::new(&Settings {
+            syntax: Syntax::Colors(SyntaxColors {
+                comment: Some(comment),
+                ..SyntaxColors::default()
+            }),
+            token: "rs".to_string(),
+            tag: None
+        });
+        let colors: Vec<_> = highlighter
+            .highlight_line("let a = 1; // one")
+            .map(|(range, highlight)| (range, highlight.to_format().color))
+            .collect();
+        for (range, color) in colors {
+            assert_eq!(color, (range.start >= 11).then_some(comment));
+        }
+        assert_eq!(highlighter.current_line(), 1);
+
+        let mut highlighter = Highlighter::new(&Settings {
+            syntax: Syntax::Preset(HighlighterTheme::Base16Mocha),
+            token: "rs".to_string(),
+            tag: None
+        });
+        assert!(
+            highlighter
+                .highlight_line("// one")
+                .any(|(_, highlight)| highlight.to_format().color.is_some())
+        );
+    }
+}
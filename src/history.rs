@@ -0,0 +1,285 @@
+use std::{
+    fs,
+    io::{ErrorKind, Read, Write}
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use iced::{
+    Font, Length,
+    keyboard::{self, Key, key::Named},
+    widget::{button, column, container, row, scrollable, text}
+};
+use jiff::{SignedDuration, Timestamp, tz::TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Element, Path, PathBuf,
+    config::HistoryConfig,
+    diff,
+    error::{Error, Result},
+    theme::Palette,
+    vault
+};
+
+const HISTORY: &str = ".pokisona/history";
+
+/// A version of a file saved when it was written, its content is stored compressed under its
+/// hash so identical versions are only stored once
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub saved: Timestamp,
+    pub hash: String
+}
+
+/// Snapshots of a file, oldest first
+#[derive(Default, Serialize, Deserialize)]
+struct Index {
+    #[serde(default)]
+    snapshots: Vec<Snapshot>
+}
+
+/// Where the snapshots of a file are kept, files outside of the vault have no history
+fn dir(path: &Path) -> Option<PathBuf> {
+    vault::is_inside(path).then(|| Path::new(HISTORY).join(path))
+}
+
+fn load(dir: &Path) -> Result<Index> {
+    match fs::read_to_string(dir.join("index.toml")) {
+        Ok(index) => toml::from_str(&index)
+            .map_err(|error| Error::InvalidHistory(error.message().to_string())),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(Index::default()),
+        Err(error) => Err(error.into())
+    }
+}
+
+/// Stores a snapshot of a file unless it's the same as the last one, then applies the retention
+/// policy
+pub fn snapshot(path: &Path, text: &str, config: &HistoryConfig) -> Result<()> {
+    let Some(dir) = dir(path).filter(|_| config.enabled) else {
+        return Ok(());
+    };
+
+    let mut index = load(&dir)?;
+    let hash = blake3::hash(text.as_bytes()).to_hex().to_string();
+    if index
+        .snapshots
+        .last()
+        .is_some_and(|snapshot| snapshot.hash == hash)
+    {
+        return Ok(());
+    }
+
+    fs::create_dir_all(&dir)?;
+    let object = dir.join(format!("{hash}.gz"));
+    if !object.exists() {
+        // Written under another name first, a half written object would be reused by later
+        // snapshots
+        let temp = dir.join(format!("{hash}.gz.tmp"));
+        let mut encoder = GzEncoder::new(fs::File::create(&temp)?, Compression::default());
+        encoder.write_all(text.as_bytes())?;
+        encoder.finish()?.sync_all()?;
+        fs::rename(temp, object)?;
+    }
+
+    index.snapshots.push(Snapshot {
+        saved: Timestamp::now(),
+        hash
+    });
+    prune(&dir, &mut index, config)?;
+    let index =
+        toml::to_string(&index).map_err(|error| Error::InvalidHistory(error.to_string()))?;
+    fs::write(dir.join("index.toml"), index)?;
+    Ok(())
+}
+
+/// Deletes snapshots that are too old or too many, the newest one is always kept
+fn prune(dir: &Path, index: &mut Index, config: &HistoryConfig) -> Result<()> {
+    let cutoff = Timestamp::now()
+        .checked_sub(SignedDuration::from_hours(24 * i64::from(config.days)))
+        .unwrap_or(Timestamp::MIN);
+    let len = index.snapshots.len();
+    let first = len.saturating_sub(config.snapshots);
+    let mut position = 0;
+    index.snapshots.retain(|snapshot| {
+        let keep = position + 1 == len || (position >= first && snapshot.saved >= cutoff);
+        position += 1;
+        keep
+    });
+
+    for entry in dir.read_dir_utf8()? {
+        let entry = entry?;
+        let Some(hash) = entry.file_name().strip_suffix(".gz") else {
+            continue;
+        };
+
+        if !index.snapshots.iter().any(|snapshot| snapshot.hash == hash) {
+            fs::remove_file(entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Snapshots of a file, newest first
+pub fn list(path: &Path) -> Result<Vec<Snapshot>> {
+    let Some(dir) = dir(path) else {
+        return Ok(Vec::new());
+    };
+
+    let mut snapshots = load(&dir)?.snapshots;
+    snapshots.reverse();
+    Ok(snapshots)
+}
+
+pub fn read(path: &Path, snapshot: &Snapshot) -> Result<String> {
+    let dir = dir(path).ok_or(Error::NoHistory)?;
+    let mut text = String::new();
+    GzDecoder::new(fs::File::open(dir.join(format!("{}.gz", snapshot.hash)))?)
+        .read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Moves the history along with its file
+pub fn rename(old: &Path, new: &Path) -> Result<()> {
+    let (Some(old), Some(new)) = (dir(old), dir(new)) else {
+        return Ok(());
+    };
+
+    if !old.exists() || new.exists() {
+        return Ok(());
+    }
+
+    if let Some(parent) = new.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::rename(old, new)?;
+    Ok(())
+}
+
+/// Lists the snapshots of a file with a preview of what restoring one would change
+pub struct Picker {
+    pub path: PathBuf,
+    snapshots: Vec<Snapshot>,
+    selected: usize,
+    /// The content of the buffer when the picker was opened
+    current: String,
+    /// The content of the selected snapshot
+    pub text: String,
+    preview: String
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Select(usize),
+    Up,
+    Down,
+    Restore,
+    Close
+}
+
+impl Picker {
+    const SPACING: f32 = 10.0;
+    const LIST_WIDTH: f32 = 200.0;
+
+    pub fn new(path: PathBuf, current: String) -> Result<Self> {
+        let snapshots = list(&path)?;
+        if snapshots.is_empty() {
+            return Err(Error::NoHistory);
+        }
+
+        let mut picker = Self {
+            path,
+            snapshots,
+            selected: 0,
+            current,
+            text: String::new(),
+            preview: String::new()
+        };
+        picker.select(0)?;
+        Ok(picker)
+    }
+
+    /// The message of a key pressed while the picker is open
+    pub fn key(event: keyboard::Event) -> Option<Message> {
+        let keyboard::Event::KeyPressed { key, .. } = event else {
+            return None;
+        };
+
+        match key.as_ref() {
+            Key::Named(Named::ArrowUp) | Key::Character("k") => Some(Message::Up),
+            Key::Named(Named::ArrowDown) | Key::Character("j") => Some(Message::Down),
+            Key::Named(Named::Enter) => Some(Message::Restore),
+            Key::Named(Named::Escape) | Key::Character("q") => Some(Message::Close),
+            _ => None
+        }
+    }
+
+    /// Moves the selection up or down, stopping at the ends
+    pub fn move_selection(&mut self, offset: isize) -> Result<()> {
+        let index = self
+            .selected
+            .saturating_add_signed(offset)
+            .min(self.snapshots.len() - 1);
+        self.select(index)
+    }
+
+    pub fn select(&mut self, index: usize) -> Result<()> {
+        self.text = read(&self.path, &self.snapshots[index])?;
+        self.preview = diff::unified(&self.current, &self.text);
+        self.selected = index;
+        Ok(())
+    }
+
+    pub fn view(&self, palette: &Palette) -> Element<'_, Message> {
+        let snapshots = scrollable(column(self.snapshots.iter().enumerate().map(
+            |(index, snapshot)| {
+                let saved = snapshot.saved.to_zoned(TimeZone::system());
+                button(text(saved.strftime("%Y-%m-%d %H:%M:%S").to_string()))
+                    .on_press(Message::Select(index))
+                    .style(if index == self.selected {
+                        button::primary
+                    } else {
+                        button::text
+                    })
+                    .width(Length::Fill)
+                    .into()
+            }
+        )))
+        .width(Self::LIST_WIDTH);
+
+        let preview: Element<'_, Message> = if self.preview.is_empty() {
+            text("Same as the buffer").color(palette.subtext).into()
+        } else {
+            scrollable(column(self.preview.lines().map(|line| {
+                let color = match line.chars().next() {
+                    Some('+') => palette.success,
+                    Some('-') => palette.danger,
+                    Some('@') => palette.subtext,
+                    _ => palette.text
+                };
+                text(line).font(Font::MONOSPACE).color(color).into()
+            })))
+            .width(Length::Fill)
+            .into()
+        };
+
+        let actions = row![
+            button("Restore").on_press(Message::Restore),
+            button("Cancel")
+                .on_press(Message::Close)
+                .style(button::secondary)
+        ]
+        .spacing(Self::SPACING);
+
+        let picker = column![
+            text(format!("History of {}", self.path)).size(24),
+            row![snapshots, preview]
+                .spacing(Self::SPACING)
+                .height(Length::Fill),
+            actions
+        ]
+        .spacing(Self::SPACING);
+        container(picker).padding(Self::SPACING).into()
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs
+};
+
+use serde::Serialize;
+
+use crate::{Path, PathBuf, error::Result, markdown::Markdown, vault};
+
+/// Links and tags of every note in a vault
+pub struct Index {
+    pub notes: BTreeMap<PathBuf, Note>,
+    extension: String
+}
+
+#[derive(Default)]
+pub struct Note {
+    pub links: Vec<NoteLink>,
+    pub tags: Vec<String>
+}
+
+/// A `[[link]]` inside a note
+#[derive(Clone, Serialize)]
+pub struct NoteLink {
+    pub target: String,
+    pub line: usize,
+    pub column: usize
+}
+
+/// A line of a note matching a search
+#[derive(Serialize)]
+pub struct Match {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub text: String
+}
+
+impl Index {
+    /// Parses every note in the vault, paths are relative to the vault root
+    pub fn build(root: &Path, extension: &str) -> Result<Self> {
+        let mut index = Self {
+            notes: BTreeMap::new(),
+            extension: extension.to_string()
+        };
+        for path in vault::files(root)?
+            .into_iter()
+            .filter(|path| vault::is_note(path, extension))
+        {
+            let source = fs::read_to_string(&path)?;
+            let path = path
+                .strip_prefix(root)
+                .expect("files are inside the vault")
+                .to_path_buf();
+            index.notes.insert(path, Note::parse(&source));
+        }
+
+        Ok(index)
+    }
+
+    /// Finds the note a link points to, either by its path without extension or its file name
+    pub fn resolve(&self, target: &str) -> Option<&Path> {
+        let target = target.split('#').next()?.trim();
+        let target = target
+            .strip_suffix(&format!(".{}", self.extension))
+            .unwrap_or(target);
+        self.notes
+            .keys()
+            .find(|path| path.with_extension("") == target)
+            .or_else(|| {
+                self.notes
+                    .keys()
+                    .find(|path| path.file_stem() == Some(target))
+            })
+            .map(PathBuf::as_path)
+    }
+
+    /// Every link in the vault along with the note it's in
+    pub fn links(&self) -> impl Iterator<Item = (&Path, &NoteLink)> {
+        self.notes
+            .iter()
+            .flat_map(|(path, note)| note.links.iter().map(move |link| (path.as_path(), link)))
+    }
+
+    /// Links pointing to a note
+    pub fn backlinks(&self, note: &Path) -> Vec<(&Path, &NoteLink)> {
+        self.links()
+            .filter(|(_, link)| self.resolve(&link.target) == Some(note))
+            .collect()
+    }
+
+    /// Points the links of a note to moved notes at their new paths, none if there are none
+    pub fn rename_links(&self, source: &str, moved: &[(PathBuf, PathBuf)]) -> Option<String> {
+        let mut text = source.to_string();
+        // Replaced back to front so the spans of the earlier links stay valid
+        for link in Markdown::parse(source).links().into_iter().rev() {
+            let range = link.target.into_range();
+            if let Some(target) = self.renamed_target(&source[range.clone()], moved) {
+                text.replace_range(range, &target);
+            }
+        }
+
+        (text != source).then_some(text)
+    }
+
+    /// The target of a link to a moved note, linking by path or by file name like before
+    fn renamed_target(&self, target: &str, moved: &[(PathBuf, PathBuf)]) -> Option<String> {
+        let note = self.resolve(target)?;
+        let (_, new) = moved.iter().find(|(old, _)| old == note)?;
+        let (name, heading) = match target.split_once('#') {
+            Some((name, heading)) => (name.trim(), format!("#{heading}")),
+            None => (target.trim(), String::new())
+        };
+
+        let extension = format!(".{}", self.extension);
+        let mut renamed = if name.contains('/') {
+            new.with_extension("").into_string()
+        } else {
+            new.file_stem()?.to_string()
+        };
+        if name.ends_with(&extension) {
+            renamed.push_str(&extension);
+        }
+
+        Some(renamed + &heading)
+    }
+
+    /// Links pointing to notes that don't exist, links to headings of the same note are fine
+    pub fn broken_links(&self) -> Vec<(&Path, &NoteLink)> {
+        self.links()
+            .filter(|(_, link)| {
+                !link.target.starts_with('#') && self.resolve(&link.target).is_none()
+            })
+            .collect()
+    }
+
+    /// Notes carrying each tag
+    pub fn tags(&self) -> BTreeMap<&str, Vec<&Path>> {
+        let mut tags = BTreeMap::<_, Vec<_>>::new();
+        for (path, note) in &self.notes {
+            for tag in &note.tags {
+                tags.entry(tag.as_str()).or_default().push(path.as_path());
+            }
+        }
+
+        tags
+    }
+
+    /// Every tag along with its parents, `project` is listed when only `project/a` is used
+    pub fn tag_tree(&self) -> BTreeSet<&str> {
+        let mut tags = BTreeSet::new();
+        for tag in self.notes.values().flat_map(|note| &note.tags) {
+            tags.extend(
+                tag.match_indices('/')
+                    .map(|(index, _)| &tag[..index])
+                    .chain([tag.as_str()])
+            );
+        }
+
+        tags
+    }
+
+    /// Notes carrying a tag or one of its children like `tag/child`
+    pub fn tagged(&self, tag: &str) -> Vec<&Path> {
+        self.notes
+            .iter()
+            .filter(|(_, note)| {
+                note.tags.iter().any(|other| {
+                    other
+                        .strip_prefix(tag)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+                })
+            })
+            .map(|(path, _)| path.as_path())
+            .collect()
+    }
+}
+
+impl Note {
+    pub fn parse(source: &str) -> Self {
+        let markdown = Markdown::parse(source);
+        let links = markdown
+            .links()
+            .into_iter()
+            .map(|link| {
+                let (line, column) = line_column(source, link.target.start);
+                NoteLink {
+                    target: source[link.target.into_range()].to_string(),
+                    line,
+                    column
+                }
+            })
+            .collect();
+
+        let mut tags: Vec<_> = markdown
+            .tags()
+            .into_iter()
+            .map(|span| source[span.into_range()].to_string())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        Self { links, tags }
+    }
+}
+
+/// Case-insensitively searches every note in the vault for a pattern
+pub fn search(root: &Path, extension: &str, pattern: &str) -> Result<Vec<Match>> {
+    let pattern = pattern.to_lowercase();
+    let mut matches = Vec::new();
+    for path in vault::files(root)?
+        .into_iter()
+        .filter(|path| vault::is_note(path, extension))
+    {
+        let source = fs::read_to_string(&path)?;
+        let path = path.strip_prefix(root).expect("files are inside the vault");
+        for (line, text) in source.lines().enumerate() {
+            let lowercase = text.to_lowercase();
+            if let Some(index) = lowercase.find(&pattern) {
+                matches.push(Match {
+                    path: path.to_path_buf(),
+                    line: line + 1,
+                    column: lowercase[..index].chars().count() + 1,
+                    text: text.to_string()
+                });
+            }
+        }
+    }
+
+    Ok(matches)
+}
+
+/// One-based line and column of a byte offset
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        Path, PathBuf,
+        index::{Index, Note}
+    };
+
+    #[test]
+    fn test_parse_note() {
+        let note =
+            Note::parse("See [[other|the other note]] and **[[nested]]** #idea\n#idea #project/a");
+        let links: Vec<_> = note
+            .links
+            .iter()
+            .map(|link| (link.target.as_str(), link.line, link.column))
+            .collect();
+        assert_eq!(links, [("other", 1, 7), ("nested", 1, 38)]);
+        assert_eq!(note.tags, ["idea", "project/a"]);
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        let note = Note::parse(
+            "---\ntitle: #not-a-tag\ntags: [draft, \"#area/work\"]\naliases:\n  - alias\n---\n\
+             # Heading #skipped\n#tag and issue#2 #2024 `#code`\n```\n#fenced\n```\n"
+        );
+        assert_eq!(note.tags, ["area/work", "draft", "tag"]);
+
+        let note = Note::parse("---\ntags:\n  - a\n  - b/c\n---\n");
+        assert_eq!(note.tags, ["a", "b/c"]);
+    }
+
+    #[test]
+    fn test_tagged() {
+        let index = Index {
+            notes: BTreeMap::from([
+                ("a.md".into(), Note::parse("#project")),
+                ("b.md".into(), Note::parse("#project/plan")),
+                ("c.md".into(), Note::parse("#projects"))
+            ]),
+            extension: "md".to_string()
+        };
+        assert_eq!(
+            index.tagged("project"),
+            [Path::new("a.md"), Path::new("b.md")]
+        );
+        assert_eq!(index.tagged("project/plan"), [Path::new("b.md")]);
+        assert_eq!(
+            index.tag_tree().into_iter().collect::<Vec<_>>(),
+            ["project", "project/plan", "projects"]
+        );
+    }
+
+    #[test]
+    fn test_renamed_target() {
+        let index = Index {
+            notes: BTreeMap::from(
+                ["idea.md", "projects/plan.md"].map(|path| (path.into(), Note::default()))
+            ),
+            extension: "md".to_string()
+        };
+        let moved = [
+            (
+                PathBuf::from("idea.md"),
+                PathBuf::from("archive/old idea.md")
+            ),
+            (
+                PathBuf::from("projects/plan.md"),
+                PathBuf::from("plans/plan.md")
+            )
+        ];
+        let renamed = |target| index.renamed_target(target, &moved);
+        assert_eq!(renamed("idea").as_deref(), Some("old idea"));
+        assert_eq!(
+            renamed("idea.md#Heading").as_deref(),
+            Some("old idea.md#Heading")
+        );
+        assert_eq!(renamed("projects/plan").as_deref(), Some("plans/plan"));
+        assert_eq!(renamed("plan").as_deref(), Some("plan"));
+        assert_eq!(renamed("missing"), None);
+        assert_eq!(
+            index
+                .rename_links("See [[idea|this]] and [[projects/plan]]", &moved)
+                .as_deref(),
+            Some("See [[old idea|this]] and [[plans/plan]]")
+        );
+        assert_eq!(index.rename_links("See [[missing]]", &moved), None);
+    }
+}
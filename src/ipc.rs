@@ -0,0 +1,75 @@
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    os::unix::net::UnixStream
+};
+
+use iced::{
+    futures::{SinkExt, Stream, channel::mpsc},
+    stream
+};
+use serde::{Deserialize, Serialize};
+use smol::{
+    io::{AsyncBufReadExt, BufReader},
+    net::unix::UnixListener
+};
+
+use crate::{
+    cli::Location,
+    error::{Error, Result}
+};
+
+/// Socket of the window running in a vault, relative to the vault root
+const SOCKET: &str = ".pokisona/pokisona.sock";
+
+/// What the cli can ask an already running window to do
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Request {
+    Open(Location),
+    /// Runs a command line like the ones entered in command mode
+    Command(String),
+    Focus
+}
+
+/// Sends a request to the window running in the current vault, returns false if there's none
+pub fn send(request: &Request) -> Result<bool> {
+    let mut stream = match UnixStream::connect(SOCKET).map_err(|error| error.kind()) {
+        Ok(stream) => stream,
+        Err(ErrorKind::NotFound | ErrorKind::ConnectionRefused) => return Ok(false),
+        Err(kind) => return Err(Error::IO(kind))
+    };
+
+    let mut line =
+        serde_json::to_string(request).map_err(|error| Error::InvalidRequest(error.to_string()))?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(true)
+}
+
+/// Listens for requests on the socket of the current vault
+pub fn listen() -> impl Stream<Item = Result<Request>> {
+    stream::channel(16, |mut output: mpsc::Sender<_>| async move {
+        // A socket of a running window would've received the request instead of starting this
+        // one, so whatever is left is stale
+        let _ = fs::remove_file(SOCKET);
+        let listener = match UnixListener::bind(SOCKET) {
+            Ok(listener) => listener,
+            Err(error) => {
+                let _ = output.send(Err(error.into())).await;
+                return;
+            }
+        };
+
+        loop {
+            let request = receive(&listener).await;
+            let _ = output.send(request).await;
+        }
+    })
+}
+
+async fn receive(listener: &UnixListener) -> Result<Request> {
+    let (stream, _) = listener.accept().await?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).await?;
+    serde_json::from_str(&line).map_err(|error| Error::InvalidRequest(error.to_string()))
+}
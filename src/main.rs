@@ -1,46 +1,160 @@
 #![deny(clippy::disallowed_types)]
+mod atomic;
+mod buffer;
+mod chooser;
 mod cli;
 mod command;
+mod config;
+mod diff;
 mod error;
+mod explorer;
+mod git;
+mod help;
+mod highlighter;
+mod history;
+mod index;
+mod ipc;
 mod markdown;
 mod mode;
+mod note;
+mod session;
+mod shell;
+mod state;
+mod tags;
+mod theme;
+mod trash;
+mod vault;
+mod watch;
 
-use std::{fs, mem};
+use std::{
+    collections::{HashMap, HashSet},
+    fs, mem,
+    sync::Arc,
+    time::{Duration, Instant}
+};
 
 use camino::{Utf8Path, Utf8PathBuf};
-use catppuccin::PALETTE;
 use iced::{
-    Border, Font, Length, Theme, exit, padding,
+    Border, Event, Font, Length, Subscription, Theme, event, exit, keyboard, mouse, padding,
+    system, time,
     widget::{
-        self, Button, Id, button, column, container,
+        self, Button, PaneGrid, button, column, container,
         operation::focus,
-        row,
-        text::{self, Wrapping},
-        text_editor::{self, Content}
-    }
+        pane_grid, row,
+        text::{self, Text, Wrapping},
+        text_editor::{self, Content, Cursor, Edit, Position}
+    },
+    window
 };
 use lucide_icons::{Icon, LUCIDE_FONT_BYTES};
 
 use crate::{
-    cli::{InitialFile, VaultName},
-    command::Command,
+    buffer::{Buffer, BufferKind, Disk, File},
+    chooser::Chooser,
+    cli::{InitialFile, Location, RestoreSession, VaultName},
+    command::{Command, CommandRegistry},
+    config::{Config, SCALE_RANGE},
     error::{Error, Result},
-    mode::Mode
+    explorer::Explorer,
+    git::LineChange,
+    highlighter::Highlighter,
+    index::Index,
+    mode::{Mode, Prefix},
+    session::Session,
+    state::State,
+    theme::{Palette, Syntax},
+    vault::Vaults
 };
 
 struct Pokisona {
     vault_name: String,
+    /// Shown instead of the editor until a vault is chosen
+    chooser: Option<Chooser>,
+    /// Shown instead of the editor while browsing the snapshots of a file
+    history: Option<history::Picker>,
+    /// Shown instead of the editor while browsing the tags of the vault
+    tags: Option<tags::Picker>,
+    /// The vault tree next to the editor
+    explorer: Explorer,
     bottom_bar: BottomBar,
-    file: Option<File>,
+    buffers: Vec<Buffer>,
+    /// The buffer of the focused pane
+    current: usize,
+    tabs: Vec<Tab>,
+    tab: usize,
+    /// A key like `Ctrl-w` that was pressed and picks what the next key does
+    prefix: Option<Prefix>,
+    commands: CommandRegistry,
+    config: Config,
+    theme_name: String,
+    /// Colors of the active theme
+    palette: Palette,
+    /// Whether the desktop prefers dark themes, used by the `auto` theme
+    dark: bool,
     scale: f32,
-    editor_content: text_editor::Content,
+    /// Held modifiers, used for Ctrl+scroll zooming
+    modifiers: keyboard::Modifiers,
+    /// Time of the last edit that hasn't been autosaved yet, autosave waits until it's idle
+    last_edit: Option<Instant>,
+    /// Checked out git branch, none if the vault isn't a repository
+    branch: Option<String>,
+    /// Committed versions of the open files for the overview bar, none while loading or if the file
+    /// isn't committed
+    heads: HashMap<PathBuf, Option<String>>,
+    /// Whether to reopen the files of the last session once a vault is chosen
+    restore_session: bool,
     mode: Mode
 }
 
-#[derive(Clone)]
-struct File {
-    edited: bool,
-    path: PathBuf
+/// A workspace with its own pane layout
+struct Tab {
+    panes: pane_grid::State<Pane>,
+    focused_pane: pane_grid::Pane
+}
+
+impl Tab {
+    fn new(buffer: usize) -> Self {
+        let (panes, focused_pane) = pane_grid::State::new(Pane::new(buffer));
+        Self {
+            panes,
+            focused_pane
+        }
+    }
+}
+
+/// An editor in the split layout
+struct Pane {
+    buffer: usize,
+    /// Own copy of the content when another pane draws the buffer, so each pane keeps its own
+    /// cursor and scroll
+    content: Option<Content>,
+    /// Lines scrolled down from the top. The editor doesn't expose its scroll offset, so it's
+    /// counted from the scroll actions
+    scroll: usize,
+    /// The scroll and cursor from the session, applied once the editor was laid out
+    restore: Option<(usize, Position)>,
+    /// The reloads of the buffer the copy has followed
+    reloads: usize
+}
+
+impl Pane {
+    fn new(buffer: usize) -> Self {
+        Self {
+            buffer,
+            content: None,
+            scroll: 0,
+            restore: None,
+            reloads: 0
+        }
+    }
+
+    /// The content the pane draws
+    fn content_mut<'a>(&'a mut self, buffers: &'a mut [Buffer]) -> &'a mut Content {
+        match &mut self.content {
+            Some(content) => content,
+            None => &mut buffers[self.buffer].content
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -56,56 +170,141 @@ type Task<M = Message> = iced::Task<M>;
 #[derive(Clone)]
 enum Message {
     EnterCommandMode,
+    /// Enters command mode with the command line prefilled
+    EnterCommandModeWith(&'static str),
     ExitCommandMode,
     Command(Command),
     SwitchMode(Mode),
     EditCommand(String),
     SubmitCommand,
-    EditorAction(text_editor::Action)
+    EditorAction(pane_grid::Pane, text_editor::Action),
+    PaneClicked(pane_grid::Pane),
+    PaneResized(pane_grid::ResizeEvent),
+    /// Waits for the key following a prefix, or stops waiting
+    Prefix(Option<Prefix>),
+    /// A key without a binding was pressed after a prefix
+    UnboundKey(String),
+    SelectTab(usize),
+    /// The output of a shell command and the commands that run after it
+    ShellOutput(shell::Output, shell::Target, Result<String>, Vec<Command>),
+    Chooser(chooser::Message),
+    History(history::Message),
+    Tags(tags::Message),
+    Explorer(explorer::Message),
+    /// A request from the cli run in the same vault
+    Remote(Result<ipc::Request>),
+    /// One of the config files changed
+    ConfigChanged(Result<()>),
+    SystemTheme(iced::theme::Mode),
+    ModifiersChanged(keyboard::Modifiers),
+    WheelScrolled(mouse::ScrollDelta),
+    SaveSession,
+    Autosave,
+    Unfocused,
+    FileChanged(Result<Vec<PathBuf>>),
+    Git(Result<String>),
+    GitBranch(Result<Option<String>>),
+    GitHead(PathBuf, Result<Option<String>>),
+    /// Scrolls the panes restored from the session, sent once they were laid out
+    RestoreScroll,
+    CloseRequested
 }
 
 type PathBuf = Utf8PathBuf;
-#[allow(dead_code)]
 type Path = Utf8Path;
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
-    let (VaultName(vault_name), InitialFile(path)) = cli::handle_args()?;
-    let (file, bottom_bar, content) = match path {
-        Some(path) => match fs::read_to_string(&path) {
-            Ok(content) => (
-                Some(File {
-                    edited: false,
-                    path
-                }),
-                BottomBar::None,
-                text_editor::Content::with_text(&content)
-            ),
-            Err(error) => (
-                None,
-                BottomBar::Error(error.into()),
-                text_editor::Content::new()
-            )
-        },
-        None => (None, BottomBar::None, text_editor::Content::new())
+    let (vault_name, InitialFile(location), RestoreSession(restore_session)) = cli::handle_args()?;
+    let (vault_name, chooser) = match vault_name {
+        Some(VaultName(vault_name)) => (vault_name, None),
+        None => (String::new(), Some(Chooser::new(Vaults::load()?)?))
+    };
+
+    // The config of the vault is loaded once it's chosen
+    let vault = chooser.is_none().then(|| Path::new("."));
+    let (config, bottom_bar) = match Config::load(vault) {
+        Ok(config) => (config, BottomBar::None),
+        Err(error) => (Config::default(), BottomBar::Error(error))
+    };
+
+    let (commands, bottom_bar) = match CommandRegistry::new(config.aliases.clone()) {
+        Ok(commands) => (commands, bottom_bar),
+        Err(error) => (CommandRegistry::default(), BottomBar::Error(error.into()))
+    };
+
+    let (state, bottom_bar) = match &chooser {
+        Some(_) => (State::default(), bottom_bar),
+        None => match State::load() {
+            Ok(state) => (state, bottom_bar),
+            Err(error) => (State::default(), BottomBar::Error(error))
+        }
     };
 
     iced::application(
         move || {
-            let pokisona = Pokisona {
+            let mut pokisona = Pokisona {
                 vault_name: vault_name.clone(),
-                file: file.clone(),
+                chooser: chooser.clone(),
+                history: None,
+                tags: None,
+                explorer: Explorer::default(),
+                buffers: vec![Buffer::scratch()],
+                current: 0,
+                tabs: vec![Tab::new(0)],
+                tab: 0,
+                prefix: None,
+                commands: commands.clone(),
+                config: config.clone(),
+                theme_name: config.theme.clone(),
+                palette: Palette::resolve(&config.theme, &config.themes, true)
+                    .expect("the theme is validated when loading the config"),
+                dark: true,
                 mode: Mode::Normal,
                 bottom_bar: bottom_bar.clone(),
-                editor_content: content.clone(),
-                scale: 1.0
+                scale: state.scale.unwrap_or(config.scale),
+                modifiers: keyboard::Modifiers::default(),
+                last_edit: None,
+                branch: None,
+                heads: HashMap::new(),
+                restore_session
             };
 
-            (pokisona, focus("editor"))
+            if restore_session
+                && pokisona.chooser.is_none()
+                && let Err(error) = pokisona.load_session()
+            {
+                pokisona.bottom_bar = BottomBar::Error(error);
+            }
+
+            if let Some(location) = location.clone()
+                && let Err(error) = pokisona.open_location(location)
+            {
+                pokisona.bottom_bar = BottomBar::Error(error);
+            }
+
+            pokisona.sync_panes();
+            let git = if pokisona.chooser.is_none() {
+                pokisona.refresh_git()
+            } else {
+                Task::none()
+            };
+            let task = Task::batch([
+                focus(Pokisona::EDITOR),
+                system::theme().map(Message::SystemTheme),
+                git
+            ]);
+            (pokisona, task)
         },
         Pokisona::update,
         Pokisona::view
     )
+    .subscription(Pokisona::subscription)
+    .window(window::Settings {
+        // Closing saves the session first
+        exit_on_close_request: false,
+        ..window::Settings::default()
+    })
     .font(LUCIDE_FONT_BYTES)
     .theme(Pokisona::theme)
     .scale_factor(|app| app.scale)
@@ -113,20 +312,53 @@ fn main() -> color_eyre::Result<()> {
     Ok(())
 }
 
-const CATPPUCCIN_MOCHA: catppuccin::FlavorColors = PALETTE.mocha.colors;
 impl Pokisona {
     fn update(&mut self, msg: Message) -> Task {
-        let task = match self.try_update(msg) {
+        // Background messages shouldn't move the focus, that would reset the cursor of the
+        // command input
+        let background = matches!(
+            msg,
+            Message::ConfigChanged(_)
+                | Message::ModifiersChanged(_)
+                | Message::WheelScrolled(_)
+                | Message::SaveSession
+                | Message::Autosave
+                | Message::Unfocused
+                | Message::FileChanged(_)
+                | Message::GitBranch(_)
+                | Message::GitHead(..)
+                | Message::RestoreScroll
+        );
+        // Anything else cancels a pending prefix
+        if !background && !matches!(msg, Message::Prefix(_) | Message::UnboundKey(_)) {
+            self.prefix = None;
+        }
+
+        let current = self.current;
+        let result = self.try_update(msg);
+        self.sync_panes();
+        self.update_overviews();
+        let result = match result {
+            // Switching buffers autosaves the one that was left
+            Ok(task) if self.current != current => Ok(Task::batch([task, self.autosave()])),
+            result => result
+        };
+        let task = match result {
             Ok(task) => task,
             Err(error) => {
                 self.bottom_bar = BottomBar::Error(error);
-                focus(Id::new("editor"))
+                focus(Self::EDITOR)
             }
         };
+        let task = Task::batch([task, self.fetch_head()]);
+
+        if self.chooser.is_some() || background {
+            return task;
+        }
 
         let focus = match self.bottom_bar {
             BottomBar::Command(_) => focus("command-input"),
-            _ => focus("editor")
+            _ => focus(Self::EDITOR)
         };
 
         Task::batch([task, focus])
@@ -149,120 +381,1493 @@ impl Pokisona {
 
                 let command = mem::take(command);
                 self.bottom_bar = BottomBar::None;
-                return self.handle_command(command.parse::<Command>()?);
+                let commands = self.commands.parse(&command)?;
+                return Ok(self.handle_commands(commands));
             }
-            Message::EditorAction(action) => {
+            Message::EditorAction(pane, action) => {
                 self.bottom_bar = BottomBar::None;
-                if let text_editor::Action::Edit(_) = &action
-                    && let Some(File { edited, .. }) = &mut self.file
+                if let text_editor::Action::Scroll { lines } = action
+                    && let Some(pane) = self.tabs[self.tab].panes.get_mut(pane)
                 {
-                    *edited = true;
+                    let last = pane.content_mut(&mut self.buffers).line_count() - 1;
+                    pane.scroll = pane.scroll.saturating_add_signed(lines as isize).min(last);
+                }
+
+                // Scrolling a pane doesn't focus it
+                if pane != self.tab().focused_pane
+                    && let text_editor::Action::Scroll { .. } = action
+                {
+                    if let Some(pane) = self.tabs[self.tab].panes.get_mut(pane) {
+                        pane.content_mut(&mut self.buffers).perform(action);
+                    }
+
+                    return Ok(Task::none());
+                }
+
+                if pane != self.tab().focused_pane {
+                    self.focus_pane(pane);
+                }
+
+                if !action.is_edit() {
+                    self.buffer_mut().perform(action);
+                    return Ok(Task::none());
                 }
 
-                self.editor_content.perform(action);
+                self.last_edit = Some(Instant::now());
+                self.edit(self.current, action);
             }
+            Message::PaneClicked(pane) => self.focus_pane(pane),
+            Message::PaneResized(pane_grid::ResizeEvent { split, ratio }) => {
+                self.tab_mut().panes.resize(split, ratio);
+            }
+            Message::Prefix(prefix) => self.prefix = prefix,
+            Message::UnboundKey(key) => {
+                let key = match self.prefix.take() {
+                    Some(prefix) => format!("{} {key}", prefix.as_ref()),
+                    None => key
+                };
+                return Err(Error::UnboundKey(key));
+            }
+            Message::SelectTab(index) => self.select_tab(index),
             Message::Command(command) => return self.handle_command(command),
             Message::EnterCommandMode => {
                 self.bottom_bar = BottomBar::Command(String::new());
             }
+            Message::EnterCommandModeWith(command) => {
+                self.bottom_bar = BottomBar::Command(command.to_string());
+            }
+            Message::ShellOutput(output, target, result, then) => {
+                self.insert_output(output, target, result?);
+                return Ok(self.handle_commands(then));
+            }
+            Message::Chooser(message) => {
+                self.bottom_bar = BottomBar::None;
+                if let Some(chooser) = &mut self.chooser
+                    && let Some(vault_name) = chooser.update(message)?
+                {
+                    self.vault_name = vault_name;
+                    self.chooser = None;
+                    self.reload_config()?;
+                    if let Some(scale) = State::load()?.scale {
+                        self.scale = scale;
+                    }
+
+                    if self.restore_session {
+                        self.load_session()?;
+                    }
+
+                    return Ok(self.refresh_git());
+                }
+            }
+            Message::History(message) => match message {
+                history::Message::Select(index) => {
+                    if let Some(picker) = &mut self.history {
+                        picker.select(index)?;
+                    }
+                }
+                history::Message::Up => {
+                    if let Some(picker) = &mut self.history {
+                        picker.move_selection(-1)?;
+                    }
+                }
+                history::Message::Down => {
+                    if let Some(picker) = &mut self.history {
+                        picker.move_selection(1)?;
+                    }
+                }
+                history::Message::Restore => {
+                    if let Some(picker) = self.history.take()
+                        && let Some(buffer) = self.buffers.iter_mut().find(|buffer| {
+                            buffer.file().is_some_and(|file| file.path == picker.path)
+                        })
+                    {
+                        buffer.reload(&picker.text);
+                        if let BufferKind::File(file) = &mut buffer.kind {
+                            file.edited = true;
+                        }
+                    }
+                }
+                history::Message::Close => self.history = None
+            },
+            Message::Tags(tags::Message::Close) => self.tags = None,
+            Message::Tags(message) => {
+                if let Some(picker) = &mut self.tags
+                    && let Some(path) = picker.update(message)
+                {
+                    self.tags = None;
+                    self.open_file(path)?;
+                }
+            }
+            Message::Explorer(message) => {
+                if let Some(action) = self.explorer.update(message)? {
+                    self.explorer_action(action)?;
+                }
+            }
+            Message::Remote(request) => return self.handle_request(request?),
+            Message::ConfigChanged(result) => {
+                result?;
+                self.reload_config()?;
+            }
+            Message::ModifiersChanged(modifiers) => self.modifiers = modifiers,
+            Message::SaveSession => self.save_session()?,
+            Message::Autosave => {
+                if self
+                    .last_edit
+                    .is_some_and(|edit| edit.elapsed().as_secs_f32() >= self.config.autosave.delay)
+                {
+                    return Ok(self.autosave());
+                }
+            }
+            Message::Unfocused => return Ok(self.autosave()),
+            Message::FileChanged(paths) => {
+                let (git, files): (Vec<_>, Vec<_>) = paths?.into_iter().partition(|path| {
+                    // Commits, checkouts and pulls move HEAD or the branch it points to
+                    path == ".git/HEAD" || path.starts_with(".git/refs/heads")
+                });
+                let task = if git.is_empty() {
+                    Task::none()
+                } else {
+                    self.refresh_git()
+                };
+                // A buffer that can't be reloaded shouldn't keep the others from it
+                let mut result = Ok(());
+                if !files.is_empty() && self.explorer.visible {
+                    result = self.explorer.refresh();
+                }
+
+                for path in files {
+                    result = result.and(self.file_changed(path));
+                }
+
+                if let Err(error) = result {
+                    self.bottom_bar = BottomBar::Error(error);
+                }
+
+                return Ok(task);
+            }
+            Message::Git(result) => {
+                result?;
+                return Ok(self.refresh_git());
+            }
+            Message::GitBranch(branch) => self.branch = branch?,
+            Message::GitHead(path, head) => {
+                self.heads.insert(path.clone(), head?);
+                for buffer in &mut self.buffers {
+                    if buffer.file().is_some_and(|file| file.path == path) {
+                        buffer.overview = None;
+                    }
+                }
+            }
+            Message::RestoreScroll => {
+                for (_, pane) in self.tabs[self.tab].panes.iter_mut() {
+                    if let Some((scroll, position)) = pane.restore.take() {
+                        let content = pane.content_mut(&mut self.buffers);
+                        content.perform(text_editor::Action::Scroll {
+                            lines: i32::try_from(scroll).unwrap_or(i32::MAX)
+                        });
+                        content.move_to(Cursor {
+                            position: buffer::clamp(content, position),
+                            selection: None
+                        });
+                        pane.scroll = scroll;
+                    }
+                }
+            }
+            Message::CloseRequested => return Ok(self.exit()),
+            Message::WheelScrolled(delta) => {
+                let (mouse::ScrollDelta::Lines { y, .. } | mouse::ScrollDelta::Pixels { y, .. }) =
+                    delta;
+                if self.modifiers.control() && y != 0.0 {
+                    let command = if y > 0.0 {
+                        Command::ZoomIn
+                    } else {
+                        Command::ZoomOut
+                    };
+
+                    return self.handle_command(command);
+                }
+            }
+            Message::SystemTheme(mode) => {
+                self.dark = !matches!(mode, iced::theme::Mode::Light);
+                self.palette = Palette::resolve(&self.theme_name, &self.config.themes, self.dark)?;
+            }
         };
 
         Ok(Task::none())
     }
 
+    fn handle_request(&mut self, request: ipc::Request) -> Result<Task> {
+        let task = match request {
+            ipc::Request::Open(location) => {
+                self.open_location(location)?;
+                Task::none()
+            }
+            ipc::Request::Command(command) => {
+                let commands = self.commands.parse(&command)?;
+                self.handle_commands(commands)
+            }
+            ipc::Request::Focus => Task::none()
+        };
+
+        Ok(task.chain(window::latest().and_then(window::gain_focus)))
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let window = Subscription::batch([
+            system::theme_changes().map(Message::SystemTheme),
+            window::close_requests().map(|_| Message::CloseRequested)
+        ]);
+        // The working directory isn't a vault until one is chosen
+        if self.chooser.is_some() {
+            return window;
+        }
+
+        // The pickers have no focusable widget, so they take the keys nothing else handled
+        let picker = if self.history.is_some() {
+            keyboard::listen()
+                .filter_map(history::Picker::key)
+                .map(Message::History)
+        } else if self.tags.is_some() {
+            keyboard::listen()
+                .filter_map(tags::Picker::key)
+                .map(Message::Tags)
+        } else {
+            Subscription::none()
+        };
+        let autosave = (self.config.autosave.enabled && self.last_edit.is_some())
+            .then(|| time::every(Self::AUTOSAVE_POLL_INTERVAL).map(|_| Message::Autosave));
+        // Scrolling needs the size of the lines, which is only known once a frame was drawn
+        let restore = self
+            .tab()
+            .panes
+            .iter()
+            .any(|(_, pane)| pane.restore.is_some())
+            .then(|| window::frames().map(|_| Message::RestoreScroll));
+
+        Subscription::batch([
+            window,
+            picker,
+            autosave.unwrap_or_else(Subscription::none),
+            restore.unwrap_or_else(Subscription::none),
+            event::listen_with(|event, _, _| match event {
+                Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                    Some(Message::ModifiersChanged(modifiers))
+                }
+                Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                    Some(Message::WheelScrolled(delta))
+                }
+                Event::Window(window::Event::Unfocused) => Some(Message::Unfocused),
+                _ => None
+            }),
+            Subscription::run(ipc::listen).map(Message::Remote),
+            Subscription::run(watch::watch).map(Message::FileChanged),
+            Subscription::run(watch::watch_config).map(Message::ConfigChanged),
+            time::every(Self::SESSION_SAVE_INTERVAL).map(|_| Message::SaveSession)
+        ])
+    }
+
+    fn reload_config(&mut self) -> Result<()> {
+        let config = Config::load(Some(Path::new(".")))?;
+        self.commands = CommandRegistry::new(config.aliases.clone())?;
+        if config.scale != self.config.scale {
+            self.scale = config.scale;
+        }
+
+        if config.theme != self.config.theme {
+            self.theme_name = config.theme.clone();
+        }
+
+        self.config = config;
+        self.palette = Palette::resolve(&self.theme_name, &self.config.themes, self.dark)?;
+        Ok(())
+    }
+
+    /// Runs commands in order, stopping at the first one that fails
+    fn handle_commands(&mut self, commands: Vec<Command>) -> Task {
+        let mut task = Task::none();
+        let mut commands = commands.into_iter();
+        while let Some(command) = commands.next() {
+            let result = match command {
+                // The commands after a shell command wait for it to finish successfully
+                Command::Shell(_)
+                | Command::Pipe(_)
+                | Command::InsertOutput(_)
+                | Command::AppendOutput(_) => {
+                    self.shell_command(command, commands.by_ref().collect())
+                }
+                command => self.handle_command(command)
+            };
+            match result {
+                Ok(next) => task = task.chain(next),
+                Err(error) => {
+                    self.bottom_bar = BottomBar::Error(error);
+                    break;
+                }
+            }
+        }
+
+        task
+    }
+
     fn handle_command(&mut self, command: Command) -> Result<Task> {
         match command {
             Command::Quit => {
-                if self.file.as_ref().is_none_or(|file| !file.edited) {
-                    Ok(exit())
-                } else {
+                if self.buffers.iter().any(Buffer::is_edited) {
                     Err(Error::CannotQuitWithUnsavedBuffer)
+                } else {
+                    Ok(self.exit())
                 }
             }
-            Command::ForceQuit => Ok(exit()),
-            Command::Write(path) => self.write(path, false, Task::none()),
-            Command::ForceWrite(path) => self.write(path, true, Task::none()),
-            Command::WriteQuit(path) => self.write(path, false, exit()),
-            Command::ForceWriteQuit(path) => self.write(path, true, exit()),
+            Command::ForceQuit => Ok(self.exit()),
+            Command::Write(path) => self.write(self.current, path, false, Task::none()),
+            Command::ForceWrite(path) => self.write(self.current, path, true, Task::none()),
+            Command::WriteQuit(path) => self.write(self.current, path, false, self.exit()),
+            Command::ForceWriteQuit(path) => self.write(self.current, path, true, self.exit()),
             Command::Open(path) => {
-                let exists = path.exists();
-                self.file = Some(File {
-                    edited: !exists,
-                    path: path.clone()
-                });
-
-                self.editor_content = Content::new();
-                if exists {
-                    self.editor_content =
-                        text_editor::Content::with_text(&fs::read_to_string(path)?);
-                }
-
+                self.open_file(path)?;
                 Ok(Task::none())
             }
             Command::Reload => {
-                if let Some(File { edited, path }) = &mut self.file {
-                    self.editor_content =
-                        text_editor::Content::with_text(&fs::read_to_string(&*path)?);
+                let buffer = self.buffer_mut();
+                if let BufferKind::File(File { edited, path, disk }) = &mut buffer.kind {
+                    let text = fs::read_to_string(&*path)?;
                     *edited = false;
+                    *disk = Some(Disk::new(path, text.clone()));
+                    buffer.reload(&text);
+                }
+
+                Ok(Task::none())
+            }
+            Command::Diff => {
+                let (base, theirs) = self.changes_on_disk()?;
+                let ours = self.buffer().content.text();
+                let diff = format!(
+                    "# Changes on disk\n\n```diff\n{}```\n\n# Changes in the buffer\n\n```diff\n{}```\n",
+                    diff::unified(&base, &theirs),
+                    diff::unified(&base, &ours)
+                );
+                self.show_read_only("diff", &diff);
+                Ok(Task::none())
+            }
+            Command::Merge => {
+                let (base, theirs) = self.changes_on_disk()?;
+                let buffer = self.buffer_mut();
+                let (merged, conflicts) = diff::merge(&base, &buffer.content.text(), &theirs);
+                buffer.reload(&merged);
+                if let BufferKind::File(File { edited, path, disk }) = &mut buffer.kind {
+                    *edited = true;
+                    *disk = Some(Disk::new(path, theirs));
+                }
+
+                if conflicts {
+                    return Err(Error::MergeConflicts);
                 }
 
                 Ok(Task::none())
             }
+            Command::History => {
+                let buffer = self.buffer();
+                let file = buffer.file().ok_or(Error::NoHistory)?;
+                self.history = Some(history::Picker::new(
+                    file.path.clone(),
+                    buffer.content.text()
+                )?);
+                Ok(Task::none())
+            }
+            Command::Tags => {
+                let index = Index::build(Path::new("."), &self.config.extension)?;
+                self.tags = Some(tags::Picker::new(&index)?);
+                Ok(Task::none())
+            }
+            Command::Remove if self.buffer().is_edited() => Err(Error::CannotRemoveUnsavedBuffer),
             Command::Remove => {
-                if let Some(File { path, .. }) = &self.file {
+                if let Some(File { path, .. }) = self.buffer().file() {
+                    trash::trash(path)?;
+                    self.close_buffer();
+                }
+
+                Ok(Task::none())
+            }
+            Command::ForceRemove => {
+                if let Some(File { path, .. }) = self.buffer().file() {
                     fs::remove_file(path)?;
-                    self.file = None;
+                    self.close_buffer();
                 }
 
                 Ok(Task::none())
             }
+            Command::TrashList => {
+                self.show_read_only("trash", &trash::render(&trash::list()?));
+                Ok(Task::none())
+            }
+            Command::TrashRestore(file) => {
+                let path = trash::restore(&file)?;
+                self.handle_command(Command::Open(path))
+            }
+            Command::TrashEmpty => {
+                trash::empty()?;
+                Ok(Task::none())
+            }
             Command::Move(new_path) => {
                 if new_path.parent().is_some_and(|path| !path.exists()) {
                     return Err(Error::MoveParentDirectoryDoesntExist);
                 }
 
-                if let Some(File { path, .. }) = &self.file
-                    && path.exists()
+                self.move_file(new_path)
+            }
+            Command::ForceMove(new_path) => {
+                if let Some(parent) = new_path.parent()
+                    && !parent.exists()
                 {
-                    fs::rename(path, &new_path)?
+                    fs::create_dir_all(parent)?;
                 }
 
-                self.file = Some(File {
-                    edited: self.file.as_ref().is_none_or(|file| file.edited),
-                    path: new_path
-                });
+                self.move_file(new_path)
+            }
+            Command::Rename(path, new_path) => {
+                self.rename(path, new_path)?;
+                Ok(Task::none())
+            }
+            Command::BufferNext => {
+                self.current = (self.current + 1) % self.buffers.len();
+                Ok(Task::none())
+            }
+            Command::BufferPrevious => {
+                self.current = self
+                    .current
+                    .checked_sub(1)
+                    .unwrap_or(self.buffers.len() - 1);
+                Ok(Task::none())
+            }
+            Command::BufferClose if self.buffer().is_edited() => {
+                Err(Error::CannotCloseUnsavedBuffer)
+            }
+            Command::BufferClose | Command::ForceBufferClose => {
+                self.close_buffer();
+                Ok(Task::none())
+            }
+            Command::VerticalSplit => {
+                self.split(pane_grid::Axis::Vertical);
+                Ok(Task::none())
+            }
+            Command::HorizontalSplit => {
+                self.split(pane_grid::Axis::Horizontal);
+                Ok(Task::none())
+            }
+            Command::WindowClose => {
+                let tab = self.tab_mut();
+                let (_, sibling) = tab
+                    .panes
+                    .close(tab.focused_pane)
+                    .ok_or(Error::CannotCloseLastPane)?;
+                self.focus_pane(sibling);
+                Ok(Task::none())
+            }
+            Command::WindowOnly => {
+                let tab = self.tab_mut();
+                let others: Vec<_> = tab
+                    .panes
+                    .iter()
+                    .map(|(pane, _)| *pane)
+                    .filter(|pane| *pane != tab.focused_pane)
+                    .collect();
+                for pane in others {
+                    tab.panes.close(pane);
+                }
 
                 Ok(Task::none())
             }
-            Command::ForceMove(new_path) => {
-                if let Some(parent) = new_path.parent()
-                    && !parent.exists()
+            Command::WindowNext => {
+                let tab = self.tab();
+                let panes: Vec<_> = tab.panes.iter().map(|(pane, _)| *pane).collect();
+                let index = panes
+                    .iter()
+                    .position(|pane| *pane == tab.focused_pane)
+                    .map_or(0, |index| (index + 1) % panes.len());
+                self.focus_pane(panes[index]);
+                Ok(Task::none())
+            }
+            Command::TabNew(path) => {
+                // A new scratch buffer, so opening a file replaces it instead of the buffer of
+                // the previous tab
+                self.buffers.push(Buffer::scratch());
+                self.tabs.push(Tab::new(self.buffers.len() - 1));
+                self.select_tab(self.tabs.len() - 1);
+                if let Some(path) = path {
+                    self.open_file(path)?;
+                }
+
+                Ok(Task::none())
+            }
+            Command::TabClose => {
+                if self.tabs.len() == 1 {
+                    return Err(Error::CannotCloseLastTab);
+                }
+
+                self.tabs.remove(self.tab);
+                self.select_tab(self.tab.min(self.tabs.len() - 1));
+                Ok(Task::none())
+            }
+            Command::TabNext => {
+                self.select_tab((self.tab + 1) % self.tabs.len());
+                Ok(Task::none())
+            }
+            Command::TabPrevious => {
+                self.select_tab(self.tab.checked_sub(1).unwrap_or(self.tabs.len() - 1));
+                Ok(Task::none())
+            }
+            Command::WindowLeft => self.focus_adjacent(pane_grid::Direction::Left),
+            Command::WindowDown => self.focus_adjacent(pane_grid::Direction::Down),
+            Command::WindowUp => self.focus_adjacent(pane_grid::Direction::Up),
+            Command::WindowRight => self.focus_adjacent(pane_grid::Direction::Right),
+            Command::Help(command) => {
+                let help = help::generate(command.as_deref(), &self.commands)?;
+                self.show_read_only("help", &help);
+                Ok(Task::none())
+            }
+            Command::Alias(name, command) => {
+                self.commands.add_alias(name, command)?;
+                Ok(Task::none())
+            }
+            Command::Shell(_)
+            | Command::Pipe(_)
+            | Command::InsertOutput(_)
+            | Command::AppendOutput(_) => self.shell_command(command, Vec::new()),
+            Command::New(title) => {
+                let path = note::create(
+                    &self.config.new_note,
+                    &self.config.extension,
+                    title.as_deref()
+                )?;
+                self.handle_command(Command::Open(path))
+            }
+            Command::Scratch => {
+                self.open_buffer(Buffer::scratch());
+                Ok(Task::none())
+            }
+            Command::Explorer => {
+                self.explorer.visible = !self.explorer.visible;
+                if self.explorer.visible {
+                    self.explorer.refresh()?;
+                } else if matches!(self.mode, Mode::Panel) {
+                    self.mode = Mode::Normal;
+                }
+
+                Ok(Task::none())
+            }
+            Command::ExplorerFocus => {
+                self.explorer.visible = true;
+                match self.buffer().file().map(|file| file.path.clone()) {
+                    Some(path) => self.explorer.reveal(&path)?,
+                    None => self.explorer.refresh()?
+                }
+
+                self.mode = Mode::Panel;
+                Ok(Task::none())
+            }
+            Command::Theme(name) => {
+                self.palette = Palette::resolve(&name, &self.config.themes, self.dark)?;
+                self.theme_name = name;
+                Ok(Task::none())
+            }
+            Command::ZoomIn => self.zoom(self.scale * Self::ZOOM_STEP),
+            Command::ZoomOut => self.zoom(self.scale / Self::ZOOM_STEP),
+            Command::ZoomReset => self.zoom(self.config.scale),
+            Command::Zoom(factor) => self.zoom(factor),
+            Command::GitCommit(message) => Ok(Task::perform(
+                git::commit(
+                    PathBuf::from("."),
+                    message.unwrap_or_else(|| "Update notes".to_string()),
+                    None
+                ),
+                Message::Git
+            )),
+            Command::GitPull => Ok(Task::perform(
+                git::pull(PathBuf::from("."), self.config.git.remote.clone()),
+                Message::Git
+            )),
+            Command::GitPush => Ok(Task::perform(
+                git::push(PathBuf::from("."), self.config.git.remote.clone()),
+                Message::Git
+            ))
+        }
+    }
+
+    /// Switches to the buffer of a file, opening it if needed
+    fn open_file(&mut self, path: PathBuf) -> Result<()> {
+        if let Some(index) = self
+            .buffers
+            .iter()
+            .position(|buffer| buffer.file().is_some_and(|file| file.path == path))
+        {
+            self.current = index;
+            return Ok(());
+        }
+
+        let exists = path.exists();
+        let (content, disk) = if exists {
+            let text = fs::read_to_string(&path)?;
+            (Content::with_text(&text), Some(Disk::new(&path, text)))
+        } else {
+            (Content::new(), None)
+        };
+
+        self.open_buffer(Buffer::new(
+            content,
+            BufferKind::File(File {
+                edited: !exists,
+                path,
+                disk
+            })
+        ));
+        Ok(())
+    }
+
+    /// Reloads clean buffers of a file changed by another program, edited ones are only reported
+    fn file_changed(&mut self, path: PathBuf) -> Result<()> {
+        let Some(buffer) = self
+            .buffers
+            .iter_mut()
+            .find(|buffer| buffer.file().is_some_and(|file| file.path == path))
+        else {
+            return Ok(());
+        };
+
+        let BufferKind::File(File {
+            edited,
+            disk: Some(disk),
+            ..
+        }) = &mut buffer.kind
+        else {
+            return Ok(());
+        };
+
+        let Some(text) = disk.changed(&path)? else {
+            return Ok(());
+        };
+
+        if *edited {
+            return Err(Error::ChangedOnDisk(path));
+        }
+
+        buffer.reload(&text);
+        buffer.kind = BufferKind::File(File {
+            edited: false,
+            disk: Some(Disk::new(&path, text)),
+            path
+        });
+        Ok(())
+    }
+
+    /// The version the current buffer is based on and the newer one on disk
+    fn changes_on_disk(&mut self) -> Result<(String, String)> {
+        let BufferKind::File(File {
+            path,
+            disk: Some(disk),
+            ..
+        }) = &mut self.buffer_mut().kind
+        else {
+            return Err(Error::NotChangedOnDisk);
+        };
+
+        let theirs = disk.changed(path)?.ok_or(Error::NotChangedOnDisk)?;
+        Ok((disk.text.clone(), theirs))
+    }
+
+    /// Opens a file with the cursor at the given line and column
+    fn open_location(&mut self, location: Location) -> Result<()> {
+        self.open_file(location.path)?;
+        if let Some(line) = location.line {
+            // The location wins over the cursor the pane had in the session
+            let tab = self.tab_mut();
+            if let Some(pane) = tab.panes.get_mut(tab.focused_pane) {
+                pane.restore = None;
+            }
+
+            self.buffer_mut().content.move_to(Cursor {
+                position: Position {
+                    line: line.saturating_sub(1),
+                    column: location.column.unwrap_or(1).saturating_sub(1)
+                },
+                selection: None
+            });
+        }
+
+        Ok(())
+    }
+
+    const AUTOSAVE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+    /// Writes every edited file if autosave is enabled
+    fn autosave(&mut self) -> Task {
+        if !self.config.autosave.enabled {
+            return Task::none();
+        }
+
+        self.last_edit = None;
+        // A buffer that can't be saved shouldn't keep the others from being saved
+        let mut tasks = Vec::new();
+        let mut errors = Vec::new();
+        for index in 0..self.buffers.len() {
+            if self.buffers[index].is_edited() {
+                match self.write(index, None, false, Task::none()) {
+                    Ok(task) => tasks.push(task),
+                    Err(error) => errors.push((self.buffers[index].title(), error))
+                }
+            }
+        }
+
+        // Reported without failing, so the tasks of the files that were saved still run
+        if !errors.is_empty() {
+            self.bottom_bar = BottomBar::Error(Error::AutosaveFailed(errors));
+        }
+
+        Task::batch(tasks)
+    }
+
+    /// Reads the branch and the committed versions of the open files again
+    fn refresh_git(&mut self) -> Task {
+        self.heads.clear();
+        for buffer in &mut self.buffers {
+            buffer.overview = None;
+        }
+
+        let paths: Vec<_> = self
+            .buffers
+            .iter()
+            .filter_map(|buffer| Some(buffer.file()?.path.clone()))
+            .collect();
+        let mut tasks: Vec<_> = paths.into_iter().map(|path| self.fetch(path)).collect();
+        tasks.push(Task::perform(
+            git::branch(PathBuf::from(".")),
+            Message::GitBranch
+        ));
+        Task::batch(tasks)
+    }
+
+    /// Compares the file buffers that changed since their last comparison with their committed
+    /// versions again, so the overview bar isn't diffed on every frame
+    fn update_overviews(&mut self) {
+        if !self.config.git.overview {
+            return;
+        }
+
+        for buffer in &mut self.buffers {
+            if buffer
+                .overview
+                .as_ref()
+                .is_some_and(|(revision, _)| *revision == buffer.revision)
+            {
+                continue;
+            }
+
+            if let Some(head) = buffer
+                .file()
+                .and_then(|file| self.heads.get(&file.path)?.as_deref())
+            {
+                let overview = git::overview(head, &buffer.content.text());
+                buffer.overview = Some((buffer.revision, overview));
+            }
+        }
+    }
+
+    /// Loads the committed version of the current file unless it's already known
+    fn fetch_head(&mut self) -> Task {
+        match self.buffer().file() {
+            Some(file) if !self.heads.contains_key(&file.path) => self.fetch(file.path.clone()),
+            _ => Task::none()
+        }
+    }
+
+    fn fetch(&mut self, path: PathBuf) -> Task {
+        // Marked as loading so it isn't requested again before it arrives
+        self.heads.insert(path.clone(), None);
+        Task::perform(git::head(PathBuf::from("."), path.clone()), move |head| {
+            Message::GitHead(path, head)
+        })
+    }
+
+    /// Saves the session and exits
+    fn exit(&self) -> Task {
+        // Not being able to save the session shouldn't prevent quitting
+        let _ = self.save_session();
+        exit()
+    }
+
+    const SESSION_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+    fn save_session(&self) -> Result<()> {
+        if self.chooser.is_some() {
+            return Ok(());
+        }
+
+        let buffers = self
+            .buffers
+            .iter()
+            .filter_map(|buffer| buffer.location(&buffer.content))
+            .collect();
+        let tabs = self
+            .tabs
+            .iter()
+            .map(|tab| self.save_layout(tab, tab.panes.layout()))
+            .collect();
+
+        Session {
+            buffers,
+            current: self.buffer().file().map(|file| file.path.clone()),
+            mode: self.mode,
+            tabs,
+            tab: self.tab,
+            explorer: self.explorer.state()
+        }
+        .save()
+    }
+
+    fn save_layout(&self, tab: &Tab, node: &pane_grid::Node) -> session::Layout {
+        match node {
+            pane_grid::Node::Split {
+                axis, ratio, a, b, ..
+            } => session::Layout::Split {
+                vertical: matches!(axis, pane_grid::Axis::Vertical),
+                ratio: *ratio,
+                a: Box::new(self.save_layout(tab, a)),
+                b: Box::new(self.save_layout(tab, b))
+            },
+            pane_grid::Node::Pane(id) => {
+                let pane = tab.panes.get(*id);
+                session::Layout::Pane(session::Pane {
+                    location: pane.and_then(|pane| {
+                        let buffer = &self.buffers[pane.buffer];
+                        buffer.location(pane.content.as_ref().unwrap_or(&buffer.content))
+                    }),
+                    scroll: pane.map_or(0, |pane| pane.scroll),
+                    focused: *id == tab.focused_pane
+                })
+            }
+        }
+    }
+
+    /// Turns a saved layout back into panes, the ones showing files that couldn't be opened show
+    /// the current buffer. Panes are counted in order to find the focused one
+    fn restore_layout(
+        &self,
+        layout: session::Layout,
+        count: &mut usize,
+        focused: &mut usize
+    ) -> pane_grid::Configuration<Pane> {
+        match layout {
+            session::Layout::Split {
+                vertical,
+                ratio,
+                a,
+                b
+            } => pane_grid::Configuration::Split {
+                axis: if vertical {
+                    pane_grid::Axis::Vertical
+                } else {
+                    pane_grid::Axis::Horizontal
+                },
+                ratio,
+                a: Box::new(self.restore_layout(*a, count, focused)),
+                b: Box::new(self.restore_layout(*b, count, focused))
+            },
+            session::Layout::Pane(saved) => {
+                if saved.focused {
+                    *focused = *count;
+                }
+
+                *count += 1;
+                let buffer = saved.location.as_ref().and_then(|location| {
+                    self.buffers
+                        .iter()
+                        .position(|buffer| {
+                            buffer.file().is_some_and(|file| file.path == location.path)
+                        })
+                        .map(|index| (index, location))
+                });
+                let mut pane = Pane::new(buffer.map_or(self.current, |(index, _)| index));
+                pane.restore = buffer.map(|(_, location)| {
+                    let position = Position {
+                        line: location.line.unwrap_or(1).saturating_sub(1),
+                        column: location.column.unwrap_or(1).saturating_sub(1)
+                    };
+                    (saved.scroll, position)
+                });
+                pane_grid::Configuration::Pane(pane)
+            }
+        }
+    }
+
+    /// The panes of a layout from left to right and top to bottom
+    fn panes(node: &pane_grid::Node, panes: &mut Vec<pane_grid::Pane>) {
+        match node {
+            pane_grid::Node::Split { a, b, .. } => {
+                Self::panes(a, panes);
+                Self::panes(b, panes);
+            }
+            pane_grid::Node::Pane(pane) => panes.push(*pane)
+        }
+    }
+
+    /// Reopens the files of the last session, files that no longer exist are skipped
+    fn load_session(&mut self) -> Result<()> {
+        let Some(session) = Session::load()? else {
+            return Ok(());
+        };
+
+        for location in session.buffers {
+            if location.path.exists() {
+                self.open_location(location)?;
+            }
+        }
+
+        if let Some(current) = session.current
+            && let Some(index) = self
+                .buffers
+                .iter()
+                .position(|buffer| buffer.file().is_some_and(|file| file.path == current))
+        {
+            self.current = index;
+        }
+
+        if !session.tabs.is_empty() {
+            self.tabs = session
+                .tabs
+                .into_iter()
+                .map(|layout| {
+                    let (mut count, mut focused) = (0, 0);
+                    let panes = pane_grid::State::with_configuration(self.restore_layout(
+                        layout,
+                        &mut count,
+                        &mut focused
+                    ));
+                    let mut order = Vec::new();
+                    Self::panes(panes.layout(), &mut order);
+                    Tab {
+                        focused_pane: order[focused],
+                        panes
+                    }
+                })
+                .collect();
+            self.tab = session.tab.min(self.tabs.len() - 1);
+            if let Some(pane) = self.tab().panes.get(self.tab().focused_pane) {
+                self.current = pane.buffer;
+            }
+
+            self.sync_panes();
+            // Restored panes start at the top, they're scrolled once they were laid out
+            for tab in &mut self.tabs {
+                for (_, pane) in tab.panes.iter_mut() {
+                    if pane.restore.is_some() {
+                        pane.content_mut(&mut self.buffers).move_to(Cursor {
+                            position: Position { line: 0, column: 0 },
+                            selection: None
+                        });
+                    }
+                }
+            }
+        }
+
+        self.explorer.restore(session.explorer)?;
+        // There's nothing for panel mode to act on while the explorer is hidden
+        self.mode = match session.mode {
+            Mode::Panel if !self.explorer.visible => Mode::Normal,
+            mode => mode
+        };
+        Ok(())
+    }
+
+    const ZOOM_STEP: f32 = 1.1;
+    /// Sets the zoom and remembers it for the vault
+    fn zoom(&mut self, scale: f32) -> Result<Task> {
+        self.scale = scale.clamp(*SCALE_RANGE.start(), *SCALE_RANGE.end());
+        let mut state = State::load()?;
+        state.scale = Some(self.scale);
+        state.save()?;
+        Ok(Task::none())
+    }
+
+    fn close_buffer(&mut self) {
+        self.remove_buffer(self.current);
+    }
+
+    /// Closes the clean buffers of the files inside a trashed path
+    fn close_trashed(&mut self, trashed: &Path) {
+        for index in (0..self.buffers.len()).rev() {
+            if self.buffers[index]
+                .file()
+                .is_some_and(|file| !file.edited && file.path.starts_with(trashed))
+            {
+                self.remove_buffer(index);
+            }
+        }
+    }
+
+    /// Removes a buffer, the panes showing it switch to the buffer taking its place
+    fn remove_buffer(&mut self, index: usize) {
+        self.buffers.remove(index);
+        if self.buffers.is_empty() {
+            self.buffers.push(Buffer::scratch());
+        }
+
+        let last = self.buffers.len() - 1;
+        let shift = |buffer: usize| (if buffer > index { buffer - 1 } else { buffer }).min(last);
+        for tab in &mut self.tabs {
+            for (_, pane) in tab.panes.iter_mut() {
+                if pane.buffer == index {
+                    pane.content = None;
+                }
+
+                pane.buffer = shift(pane.buffer);
+            }
+        }
+
+        self.current = shift(self.current);
+    }
+
+    fn tab(&self) -> &Tab {
+        &self.tabs[self.tab]
+    }
+
+    fn tab_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.tab]
+    }
+
+    /// Switches to a tab, focusing the pane that was focused in it
+    fn select_tab(&mut self, index: usize) {
+        self.tab = index;
+        self.focus_pane(self.tab().focused_pane);
+    }
+
+    /// Splits the focused pane, the new pane shows the same buffer and takes the focus. The old
+    /// pane keeps drawing the content it had, so its cursor and scroll stay where they were
+    fn split(&mut self, axis: pane_grid::Axis) {
+        let buffer = &mut self.buffers[self.current];
+        let tab = &mut self.tabs[self.tab];
+        let Some((new, _)) = tab
+            .panes
+            .split(axis, tab.focused_pane, Pane::new(self.current))
+        else {
+            return;
+        };
+
+        if let Some(old) = tab.panes.get_mut(tab.focused_pane) {
+            let copy = buffer::copy(&buffer.content);
+            old.content = Some(mem::replace(&mut buffer.content, copy));
+            old.reloads = buffer.reloads;
+        }
+
+        tab.focused_pane = new;
+    }
+
+    /// Moves the focus to a pane of the current tab, it takes over the content of its buffer
+    /// from the pane that was drawing it
+    fn focus_pane(&mut self, id: pane_grid::Pane) {
+        let tab = &mut self.tabs[self.tab];
+        let Some(pane) = tab.panes.get_mut(id) else {
+            return;
+        };
+
+        tab.focused_pane = id;
+        self.current = pane.buffer;
+        let Some(content) = pane.content.take() else {
+            return;
+        };
+
+        let buffer = pane.buffer;
+        let reloads = self.buffers[buffer].reloads;
+        let content = mem::replace(&mut self.buffers[buffer].content, content);
+        let focused = (self.tab, id);
+        if let Some((_, drawing)) = self
+            .tabs
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(index, tab)| {
+                tab.panes
+                    .iter_mut()
+                    .map(move |(id, pane)| ((index, *id), pane))
+            })
+            .find(|(other, pane)| {
+                *other != focused && pane.buffer == buffer && pane.content.is_none()
+            })
+        {
+            drawing.content = Some(content);
+            drawing.reloads = reloads;
+        }
+    }
+
+    fn focus_adjacent(&mut self, direction: pane_grid::Direction) -> Result<Task> {
+        let tab = self.tab();
+        if let Some(pane) = tab.panes.adjacent(tab.focused_pane, direction) {
+            self.focus_pane(pane);
+        }
+
+        Ok(Task::none())
+    }
+
+    /// Edits a buffer and repeats the edit in the copies of the other panes showing it, their
+    /// cursors move along with the lines inserted or removed above them
+    fn edit(&mut self, index: usize, action: text_editor::Action) {
+        let buffer = &mut self.buffers[index];
+        if buffer.is_read_only() {
+            return;
+        }
+
+        let cursor = buffer.content.cursor();
+        let (start, end) = buffer.selection_bounds();
+        let lines = buffer.content.line_count();
+        buffer.perform(action.clone());
+        let delta = buffer.content.line_count() as isize - lines as isize;
+        // Without a selection backspace and delete can join the line with its neighbour
+        let (first, last) = match action {
+            text_editor::Action::Edit(Edit::Backspace) if start == end => {
+                (start.line.saturating_sub(1), end.line)
+            }
+            text_editor::Action::Edit(Edit::Delete) if start == end => (start.line, end.line + 1),
+            _ => (start.line, end.line)
+        };
+
+        for tab in &mut self.tabs {
+            for (_, pane) in tab.panes.iter_mut() {
+                if pane.buffer == index
+                    && let Some(content) = &mut pane.content
                 {
-                    fs::create_dir_all(parent)?;
+                    let own = content.cursor();
+                    content.move_to(cursor);
+                    content.perform(action.clone());
+                    let shift = |position: Position| {
+                        let line = buffer::shift_line(position.line, first, last, delta);
+                        buffer::clamp(content, Position { line, ..position })
+                    };
+                    let cursor = Cursor {
+                        position: shift(own.position),
+                        selection: own.selection.map(shift)
+                    };
+                    content.move_to(cursor);
                 }
+            }
+        }
+    }
 
-                if let Some(File { path, .. }) = &self.file
-                    && path.exists()
+    /// Keeps the panes of every tab in line with the buffers after an update. The focused pane
+    /// shows the current buffer, and of the panes showing a buffer exactly one draws its content
+    /// while the others draw their own copy
+    fn sync_panes(&mut self) {
+        let current = self.current;
+        let tab = self.tab_mut();
+        if let Some(pane) = tab.panes.get_mut(tab.focused_pane)
+            && pane.buffer != current
+        {
+            *pane = Pane::new(current);
+        }
+
+        // Reloads and merges replace the content instead of editing it
+        for tab in &mut self.tabs {
+            for (_, pane) in tab.panes.iter_mut() {
+                let buffer = &self.buffers[pane.buffer];
+                if let Some(content) = &mut pane.content
+                    && pane.reloads != buffer.reloads
                 {
-                    fs::rename(path, &new_path)?
+                    let cursor = content.cursor();
+                    *content = Content::with_text(&buffer.content.text());
+                    content.move_to(Cursor {
+                        position: buffer::clamp(content, cursor.position),
+                        selection: None
+                    });
+                    pane.reloads = buffer.reloads;
                 }
+            }
+        }
 
-                self.file = Some(File {
-                    edited: self.file.as_ref().is_none_or(|file| file.edited),
-                    path: new_path
-                });
+        // A pane that starts showing a drawn buffer takes over the content, so the pane that was
+        // drawing it keeps its cursor and scroll
+        let focused = (self.tab, self.tab().focused_pane);
+        let mut drawn = HashSet::from([current]);
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            for (id, pane) in tab.panes.iter_mut() {
+                if (index, *id) != focused && pane.content.is_none() && !drawn.insert(pane.buffer) {
+                    let buffer = &mut self.buffers[pane.buffer];
+                    let copy = buffer::copy(&buffer.content);
+                    pane.content = Some(mem::replace(&mut buffer.content, copy));
+                    pane.reloads = buffer.reloads;
+                }
+            }
+        }
 
-                Ok(Task::none())
+        // A buffer whose drawing pane switched away is drawn by one of the copies instead
+        for tab in &mut self.tabs {
+            for (_, pane) in tab.panes.iter_mut() {
+                if !drawn.contains(&pane.buffer)
+                    && let Some(content) = pane.content.take()
+                {
+                    drawn.insert(pane.buffer);
+                    self.buffers[pane.buffer].content = content;
+                }
+            }
+        }
+    }
+
+    /// Shows generated text in a read-only buffer, reusing the buffer with the same name
+    fn show_read_only(&mut self, name: &'static str, text: &str) {
+        match self
+            .buffers
+            .iter()
+            .position(|buffer| matches!(buffer.kind, BufferKind::ReadOnly(other) if other == name))
+        {
+            Some(index) => {
+                self.current = index;
+                self.buffer_mut().reload(text);
             }
+            None => self.open_buffer(Buffer::new(
+                Content::with_text(text),
+                BufferKind::ReadOnly(name)
+            ))
         }
     }
 
-    fn write(&mut self, path: Option<PathBuf>, force: bool, task: Task) -> Result<Task> {
+    /// Starts a shell command, `then` runs once it succeeded
+    fn shell_command(&self, command: Command, then: Vec<Command>) -> Result<Task> {
+        let (command, output, input) = match command {
+            Command::Shell(command) => (command, shell::Output::Scratch, None),
+            Command::Pipe(command) => {
+                let input = self
+                    .buffer()
+                    .content
+                    .selection()
+                    .ok_or(Error::NoSelection)?;
+                (command, shell::Output::Replace, Some(input))
+            }
+            Command::InsertOutput(command) => (command, shell::Output::Insert, None),
+            Command::AppendOutput(command) => (command, shell::Output::Append, None),
+            _ => unreachable!("{command} isn't a shell command")
+        };
+        self.run_shell(command, output, input, then)
+    }
+
+    fn run_shell(
+        &self,
+        command: String,
+        output: shell::Output,
+        input: Option<String>,
+        then: Vec<Command>
+    ) -> Result<Task> {
+        let buffer = self.buffer();
+        if !matches!(output, shell::Output::Scratch) && buffer.is_read_only() {
+            return Err(Error::ReadOnlyBuffer);
+        }
+
+        let (start, end) = buffer.selection_bounds();
+        let (start, end) = match output {
+            shell::Output::Scratch | shell::Output::Replace => (start, end),
+            shell::Output::Insert => (start, start),
+            shell::Output::Append => (end, end)
+        };
+        let target = shell::Target {
+            buffer: buffer.id,
+            start,
+            end
+        };
+        Ok(Task::perform(shell::run(command, input), move |result| {
+            Message::ShellOutput(output, target, result, then)
+        }))
+    }
+
+    /// Puts the output of a command where it was meant to go, it's dropped if the target buffer
+    /// was closed in the meantime
+    fn insert_output(&mut self, output: shell::Output, target: shell::Target, text: String) {
+        if let shell::Output::Scratch = output {
+            return self.open_buffer(Buffer::new(Content::with_text(&text), BufferKind::Scratch));
+        }
+
+        let Some(index) = self
+            .buffers
+            .iter()
+            .position(|buffer| buffer.id == target.buffer)
+        else {
+            return;
+        };
+
+        // The target is selected, an empty selection only sets where the output is pasted
+        let content = &mut self.buffers[index].content;
+        let start = buffer::clamp(content, target.start);
+        let end = buffer::clamp(content, target.end);
+        content.move_to(Cursor {
+            position: end,
+            selection: Some(start)
+        });
+        self.edit(
+            index,
+            text_editor::Action::Edit(Edit::Paste(Arc::new(text)))
+        );
+    }
+
+    fn buffer(&self) -> &Buffer {
+        &self.buffers[self.current]
+    }
+
+    fn buffer_mut(&mut self) -> &mut Buffer {
+        &mut self.buffers[self.current]
+    }
+
+    /// Switches to a new buffer, replacing the current one if it's an empty scratch buffer
+    fn open_buffer(&mut self, mut buffer: Buffer) {
+        if self.buffer().is_disposable() {
+            // Other panes showing the scratch buffer follow like after a reload
+            buffer.reloads = self.buffer().reloads + 1;
+            *self.buffer_mut() = buffer;
+        } else {
+            self.buffers.push(buffer);
+            self.current = self.buffers.len() - 1;
+        }
+    }
+
+    fn move_file(&mut self, new_path: PathBuf) -> Result<Task> {
+        let buffer = self.buffer_mut();
+        if buffer.is_read_only() {
+            return Err(Error::ReadOnlyBuffer);
+        }
+
+        if let Some(File { path, .. }) = buffer.file()
+            && path.exists()
+        {
+            fs::rename(path, &new_path)?;
+            history::rename(path, &new_path)?;
+        }
+
+        buffer.kind = BufferKind::File(File {
+            edited: buffer.file().is_none_or(|file| file.edited),
+            path: new_path,
+            disk: buffer.file().and_then(|file| file.disk.clone())
+        });
+
+        Ok(Task::none())
+    }
+
+    /// Moves a file or folder of the vault, pointing the links to the notes in it at their new
+    /// paths and taking the open buffers and the history along
+    fn rename(&mut self, old: PathBuf, new: PathBuf) -> Result<()> {
+        fs::symlink_metadata(&old)?;
+        if new.exists() {
+            return Err(Error::RenameTargetExists(new));
+        }
+
+        if let Some(parent) = new.parent()
+            && parent != ""
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        let root = Path::new(".");
+        let index = Index::build(root, &self.config.extension)?;
+        let moved: Vec<_> = index
+            .notes
+            .keys()
+            .filter_map(|note| Some((note.clone(), vault::moved(note, &old, &new)?)))
+            .collect();
+        fs::rename(&old, &new)?;
+        history::rename(&old, &new)?;
+
+        for buffer in &mut self.buffers {
+            if let BufferKind::File(file) = &mut buffer.kind
+                && let Some(path) = vault::moved(&file.path, &old, &new)
+            {
+                file.path = path;
+            }
+        }
+
+        if self.explorer.visible {
+            self.explorer.reveal(&new)?;
+        }
+
+        // The links are rewritten once the notes are in place, a failed rename changes nothing
+        let mut result = Ok(());
+        for note in index.notes.keys() {
+            let path = vault::moved(note, &old, &new).unwrap_or_else(|| note.clone());
+            result = result.and(self.rename_links(&index, path, &moved));
+        }
+
+        result
+    }
+
+    /// Rewrites the links to moved notes in a note. Buffers with unsaved changes get the new links
+    /// instead of the file, writing it would conflict with them
+    fn rename_links(
+        &mut self,
+        index: &Index,
+        path: PathBuf,
+        moved: &[(PathBuf, PathBuf)]
+    ) -> Result<()> {
+        if let Some(buffer) = self.buffers.iter_mut().find(|buffer| {
+            buffer.is_edited() && buffer.file().is_some_and(|file| file.path == path)
+        }) {
+            if let Some(text) = index.rename_links(&buffer.content.text(), moved) {
+                buffer.reload(&text);
+            }
+
+            return Ok(());
+        }
+
+        let source = fs::read_to_string(&path)?;
+        if let Some(text) = index.rename_links(&source, moved) {
+            atomic::write(&path, &text, false)?;
+            // The watcher reports the note under its old path if it was moved, so an open one is
+            // reloaded here
+            self.file_changed(path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Carries out what the explorer asked for
+    fn explorer_action(&mut self, action: explorer::Action) -> Result<()> {
+        match action {
+            explorer::Action::Open(path) => self.open_file(path)?,
+            // The note is created when it's first written
+            explorer::Action::Create(folder) => {
+                self.bottom_bar = BottomBar::Command(match folder.as_str() {
+                    "" => "open ".to_string(),
+                    folder => format!("open {folder}/")
+                });
+            }
+            explorer::Action::Rename(path) => {
+                self.bottom_bar =
+                    BottomBar::Command(Command::Rename(path.clone(), path).to_string());
+            }
+            explorer::Action::Trash(path) => {
+                trash::trash(&path)?;
+                self.close_trashed(&path);
+                self.explorer.refresh()?;
+            }
+            explorer::Action::Move(path, folder) => {
+                let name = path.file_name().ok_or(Error::NoPathSet)?;
+                let new_path = folder.join(name);
+                self.rename(path, new_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write(
+        &mut self,
+        index: usize,
+        path: Option<PathBuf>,
+        force: bool,
+        task: Task
+    ) -> Result<Task> {
+        let buffer = &mut self.buffers[index];
+        if buffer.is_read_only() {
+            return Err(Error::ReadOnlyBuffer);
+        }
+
         if let Some(path) = path {
-            self.file = Some(File { edited: true, path });
+            buffer.kind = BufferKind::File(File {
+                edited: true,
+                path,
+                disk: None
+            });
         }
 
-        let Some(File { edited, path }) = &mut self.file else {
+        let BufferKind::File(File { edited, path, disk }) = &mut buffer.kind else {
             return Err(Error::NoPathSet);
         };
 
@@ -270,7 +1875,13 @@ impl Pokisona {
             return Ok(Task::none());
         }
 
-        *edited = false;
+        // Writing would silently throw away what another program wrote
+        if !force
+            && let Some(disk) = disk
+            && disk.changed(path)?.is_some()
+        {
+            return Err(Error::ChangedOnDisk(path.clone()));
+        }
 
         if let Some(parent) = path.parent()
             && parent != ""
@@ -283,89 +1894,279 @@ impl Pokisona {
             }
         }
 
-        fs::write(path, self.editor_content.text())?;
+        let text = buffer.content.text();
+        atomic::write(path, &text, self.config.backup)?;
+        // The file is written even if taking the snapshot fails
+        let snapshot = history::snapshot(path, &text, &self.config.history);
+        *edited = false;
+        *disk = Some(Disk::new(path, text));
+        snapshot?;
+
+        if self.config.git.auto_commit {
+            let commit = git::commit(
+                PathBuf::from("."),
+                format!("Update {path}"),
+                Some(path.clone())
+            );
+            return Ok(Task::batch([task, Task::perform(commit, Message::Git)]));
+        }
+
         Ok(task)
     }
 
-    const HIGHLIGHT_SCALE_ALPHA: f32 = 0.5;
-    const TEXT_EDITOR_LINE_WIDTH: f32 = 700.0;
-    const PADDING: f32 = 5.0;
     fn view(&self) -> Element<'_> {
-        let editor = widget::text_editor(&self.editor_content)
-            .style(|_, _| text_editor::Style {
-                background: CATPPUCCIN_MOCHA.base.into(),
-                border: Border::default(),
-                placeholder: CATPPUCCIN_MOCHA.text.into(),
-                value: CATPPUCCIN_MOCHA.text.into(),
-                selection: self.mode.color().scale_alpha(Self::HIGHLIGHT_SCALE_ALPHA)
-            })
-            .id("editor")
-            .highlight(
-                self.file
-                    .as_ref()
-                    .and_then(|file| file.path.extension())
-                    .unwrap_or("md"),
-                iced_highlighter::Theme::Base16Mocha
-            )
-            .on_action(Message::EditorAction)
-            .width(Self::TEXT_EDITOR_LINE_WIDTH)
-            .wrapping(Wrapping::WordOrGlyph)
-            .key_binding(self.mode.bindings())
-            .padding(Self::PADDING)
-            .height(Length::Fill);
-        let editor = container(editor).center_x(Length::Fill);
+        let palette = &self.palette;
+        if let Some(chooser) = &self.chooser {
+            return column![
+                chooser.view(palette).map(Message::Chooser),
+                self.bottom_bar()
+            ]
+            .into();
+        }
+
+        let buffer = self.buffer();
+        let editor = match (&self.history, &self.tags) {
+            (Some(picker), _) => picker.view(palette).map(Message::History),
+            (None, Some(picker)) => picker.view(palette).map(Message::Tags),
+            (None, None) => {
+                let tab = self.tab();
+                let titled = tab.panes.len() > 1;
+                let panes = PaneGrid::new(&tab.panes, |id, pane, _| {
+                    let content = pane_grid::Content::new(self.editor(id, pane)).style(|_| {
+                        container::Style {
+                            background: Some(palette.background.into()),
+                            ..Default::default()
+                        }
+                    });
+                    if !titled {
+                        return content;
+                    }
+
+                    let color = if id == tab.focused_pane {
+                        palette.text
+                    } else {
+                        palette.subtext
+                    };
+                    let title = widget::text(self.buffers[pane.buffer].title()).color(color);
+                    content.title_bar(
+                        pane_grid::TitleBar::new(title)
+                            .padding(padding::horizontal(self.config.editor.padding))
+                    )
+                })
+                .on_click(Message::PaneClicked)
+                .on_resize(Self::PANE_RESIZE_LEEWAY, Message::PaneResized)
+                .spacing(Self::PANE_SPACING);
+                let tabs = (self.tabs.len() > 1).then(|| self.tab_bar());
+                // The spacing between the panes shows as a divider
+                container(column![tabs, panes])
+                    .style(|_| container::Style {
+                        background: Some(palette.bar.into()),
+                        ..Default::default()
+                    })
+                    .into()
+            }
+        };
+        let explorer = self.explorer.visible.then(|| {
+            self.explorer
+                .view(
+                    palette,
+                    buffer.file().map(|file| file.path.as_path()),
+                    matches!(self.mode, Mode::Panel)
+                )
+                .map(Message::Explorer)
+        });
+        let editor = row![explorer, editor];
 
         let mode = container(self.mode.as_ref())
             .style(|_| container::Style {
-                text_color: Some(CATPPUCCIN_MOCHA.crust.into()),
-                background: Some(self.mode.color().into()),
+                text_color: Some(palette.bar),
+                background: Some(palette.mode(self.mode).into()),
                 ..Default::default()
             })
-            .padding(padding::horizontal(Self::PADDING));
+            .padding(padding::horizontal(self.config.editor.padding));
 
-        let save_button = self.file.as_ref().filter(|file| file.edited).map(|_| {
+        // Edits waiting for the autosave need no save button, failed autosaves do
+        let saving = buffer.is_edited() && self.config.autosave.enabled && self.last_edit.is_some();
+        let save_button = (buffer.is_edited() && !saving).then(|| {
             Self::button(Icon::Save)
                 .style(|_, status| match status {
                     button::Status::Active => button::Style {
-                        text_color: CATPPUCCIN_MOCHA.subtext0.into(),
+                        text_color: palette.subtext,
                         ..Default::default()
                     },
                     button::Status::Hovered => button::Style {
-                        text_color: self.mode.color(),
+                        text_color: palette.mode(self.mode),
                         ..Default::default()
                     },
                     button::Status::Pressed => button::Style {
-                        text_color: CATPPUCCIN_MOCHA.overlay0.into(),
+                        text_color: palette.overlay,
                         ..Default::default()
                     },
                     button::Status::Disabled => todo!()
                 })
                 .on_press(Message::Command(Command::Write(None)))
                 .padding(0)
+                .into()
         });
+        let saving: Option<Element<'_>> =
+            saving.then(|| Self::icon(Icon::LoaderCircle).color(palette.subtext).into());
 
-        let path = container(
-            self.file
-                .as_ref()
-                .map(|file| widget::text(file.path.as_str()))
-                .unwrap_or(widget::text("[scratch]").color(CATPPUCCIN_MOCHA.subtext0))
+        let path = match buffer.kind {
+            BufferKind::File(_) => widget::text(buffer.title()),
+            _ => widget::text(buffer.title()).color(palette.subtext)
+        };
+        let path = container(path).center_x(Length::Fill);
+        let prefix = self
+            .prefix
+            .map(|prefix| widget::text(prefix.as_ref().to_string()).color(palette.subtext));
+        let bar_left = container(
+            row![mode, prefix, saving.or(save_button)].spacing(self.config.editor.padding)
         )
-        .center_x(Length::Fill);
-        let bar_left =
-            container(row![mode, save_button].spacing(Self::PADDING)).width(Length::Fill);
+        .width(Length::Fill);
+        let branch = self.branch.as_deref().map(|branch| {
+            row![
+                Self::icon(Icon::GitBranch).color(palette.subtext),
+                widget::text(branch).color(palette.subtext)
+            ]
+        });
         let bar = container(row![
             bar_left,
             path,
-            container(self.vault_name.as_str())
+            container(row![branch, self.vault_name.as_str()].spacing(self.config.editor.padding))
                 .align_right(Length::Fill)
-                .padding(padding::right(Self::PADDING))
+                .padding(padding::right(self.config.editor.padding))
         ])
         .style(|_| container::Style {
-            background: Some(CATPPUCCIN_MOCHA.crust.into()),
+            background: Some(palette.bar.into()),
             ..Default::default()
         });
 
-        let bottom_bar: Option<Element<'_>> = match &self.bottom_bar {
+        column![editor, bar, self.bottom_bar()].into()
+    }
+
+    const PANE_SPACING: f32 = 2.0;
+    const PANE_RESIZE_LEEWAY: f32 = 6.0;
+    /// The id of the editor of the focused pane, which takes the keyboard focus
+    const EDITOR: &str = "editor";
+    /// The editor of a pane, centered and at most as wide as the configured line width
+    fn editor<'a>(&'a self, id: pane_grid::Pane, pane: &'a Pane) -> Element<'a> {
+        let palette = &self.palette;
+        let buffer = &self.buffers[pane.buffer];
+        let content = pane.content.as_ref().unwrap_or(&buffer.content);
+        let syntax = self
+            .config
+            .editor
+            .highlighter_theme
+            .map_or_else(|| palette.syntax.clone(), Syntax::Preset);
+        let bindings = match self.prefix {
+            Some(prefix) => prefix.bindings(),
+            None => self.mode.bindings()
+        };
+        // Focusing targets the editor of the focused pane
+        let editor = widget::text_editor(content);
+        let editor = if id == self.tab().focused_pane {
+            editor.id(Self::EDITOR)
+        } else {
+            editor
+        };
+        let editor = editor
+            .style(|_, _| text_editor::Style {
+                background: palette.background.into(),
+                border: Border::default(),
+                placeholder: palette.subtext,
+                value: palette.text,
+                selection: palette.selection(self.mode)
+            })
+            .highlight_with::<Highlighter>(
+                highlighter::Settings {
+                    syntax,
+                    token: buffer.syntax().to_string(),
+                    tag: buffer
+                        .file()
+                        .is_none_or(|file| vault::is_note(&file.path, &self.config.extension))
+                        .then(|| palette.tag.unwrap_or(palette.normal))
+                },
+                |highlight, _| highlight.to_format()
+            )
+            .on_action(move |action| Message::EditorAction(id, action))
+            .wrapping(Wrapping::WordOrGlyph)
+            .key_binding(bindings)
+            .padding(self.config.editor.padding)
+            .height(Length::Fill);
+
+        let overview = buffer
+            .overview
+            .as_ref()
+            .filter(|_| self.config.git.overview)
+            .and_then(|(_, overview)| overview.as_deref())
+            .map(|overview| self.overview_bar(overview));
+        container(container(row![overview, editor]).max_width(self.config.editor.line_width))
+            .center_x(Length::Fill)
+            .into()
+    }
+
+    /// Titles of the tabs taken from their focused panes, tabs with unsaved buffers are marked
+    fn tab_bar(&self) -> Element<'_> {
+        let palette = &self.palette;
+        row(self.tabs.iter().enumerate().map(|(index, tab)| {
+            let title = tab
+                .panes
+                .get(tab.focused_pane)
+                .map(|pane| self.buffers[pane.buffer].title())
+                .unwrap_or_default();
+            let edited = tab
+                .panes
+                .iter()
+                .any(|(_, pane)| self.buffers[pane.buffer].is_edited());
+            let active = index == self.tab;
+            let color = if active {
+                palette.text
+            } else {
+                palette.subtext
+            };
+            let edited = edited.then(|| Self::icon(Icon::Dot).color(palette.mode(self.mode)));
+            button(row![widget::text(title).color(color), edited])
+                .on_press(Message::SelectTab(index))
+                .style(move |_, _| button::Style {
+                    background: active.then(|| palette.background.into()),
+                    ..Default::default()
+                })
+                .padding(padding::horizontal(self.config.editor.padding))
+                .into()
+        }))
+        .into()
+    }
+
+    const OVERVIEW_WIDTH: f32 = 4.0;
+    /// Marks where the buffer differs from the committed version, scaled to the height of the
+    /// editor like a minimap. The editor doesn't expose where it scrolled to, so the marks can't
+    /// follow the lines on screen
+    fn overview_bar<'a>(&'a self, overview: &'a [(Option<LineChange>, usize)]) -> Element<'a> {
+        let palette = &self.palette;
+        column(overview.iter().map(|&(change, lines)| {
+            let color = change.map(|change| match change {
+                LineChange::Added => palette.success,
+                LineChange::Modified => palette.warning,
+                LineChange::Removed => palette.danger
+            });
+            container("")
+                .width(Length::Fill)
+                .height(Length::FillPortion(
+                    u16::try_from(lines).unwrap_or(u16::MAX)
+                ))
+                .style(move |_| container::Style {
+                    background: color.map(Into::into),
+                    ..Default::default()
+                })
+                .into()
+        }))
+        .width(Self::OVERVIEW_WIDTH)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn bottom_bar(&self) -> Option<Element<'_>> {
+        match &self.bottom_bar {
             BottomBar::Command(command) => Some(
                 sweeten::widget::text_input("Enter command", command)
                     .on_blur(Message::ExitCommandMode)
@@ -380,45 +2181,47 @@ impl Pokisona {
                         spacing: 0.0,
                         side: sweeten::widget::text_input::Side::Left
                     })
-                    .style(|theme: &Theme, _| sweeten::widget::text_input::Style {
-                        background: theme.palette().background.into(),
+                    .style(|_, _| sweeten::widget::text_input::Style {
+                        background: self.palette.background.into(),
                         border: Border::default(),
-                        icon: self.mode.color(),
-                        placeholder: self.theme().extended_palette().secondary.base.color,
-                        value: self.theme().palette().text,
-                        selection: self.mode.color().scale_alpha(Self::HIGHLIGHT_SCALE_ALPHA)
+                        icon: self.palette.mode(self.mode),
+                        placeholder: self.palette.subtext,
+                        value: self.palette.text,
+                        selection: self.palette.selection(self.mode)
                     })
                     .padding(0)
                     .into()
             ),
-            BottomBar::Error(error) => {
-                Some(widget::text(error.to_string()).style(text::danger).into())
-            }
+            BottomBar::Error(error) => Some(
+                widget::text(error.to_string())
+                    .color(self.palette.danger)
+                    .into()
+            ),
             BottomBar::None => None
-        };
-
-        column![editor, bar, bottom_bar].into()
+        }
     }
 
-    // TODO: use a custom theme implementation
     fn theme(&self) -> Theme {
         Theme::custom(
-            "catppuccin-mocha-custom",
+            self.theme_name.clone(),
             iced::theme::Palette {
-                background: CATPPUCCIN_MOCHA.base.into(),
-                text: CATPPUCCIN_MOCHA.text.into(),
-                primary: self.mode.color(),
-                success: CATPPUCCIN_MOCHA.green.into(),
-                warning: CATPPUCCIN_MOCHA.yellow.into(),
-                danger: CATPPUCCIN_MOCHA.red.into()
+                background: self.palette.background,
+                text: self.palette.text,
+                primary: self.palette.mode(self.mode),
+                success: self.palette.success,
+                warning: self.palette.warning,
+                danger: self.palette.danger
             }
         )
     }
 
-    fn button<'a>(icon: Icon) -> Button<'a, Message> {
-        let content = widget::text(icon.unicode())
+    fn icon<'a>(icon: Icon) -> Text<'a> {
+        widget::text(icon.unicode())
             .font(Font::with_name("lucide"))
-            .shaping(text::Shaping::Advanced);
-        button(content)
+            .shaping(text::Shaping::Advanced)
+    }
+
+    fn button<'a>(icon: Icon) -> Button<'a, Message> {
+        button(Self::icon(icon))
     }
 }
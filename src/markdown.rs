@@ -1,7 +1,15 @@
 // Simple incomplete markdown parser for prototyping
+use std::ops::Range;
+
 use bitflags::bitflags;
 use chumsky::{extra::Context, prelude::*};
-pub struct Markdown(Vec<MarkdownSpan>);
+pub struct Markdown {
+    spans: Vec<MarkdownSpan>,
+    /// The frontmatter and the heading lines, `#` in them starts no tag
+    untagged: Vec<Range<usize>>,
+    /// Tags listed under the `tags` key of the frontmatter
+    frontmatter_tags: Vec<SimpleSpan>
+}
 
 struct MarkdownSpan {
     kind: MarkdownSpanKind,
@@ -11,15 +19,141 @@ struct MarkdownSpan {
 enum MarkdownSpanKind {
     EscapedChar,
     Text,
+    // The delimiter length and the modifiers are for rendering, which doesn't exist yet
+    #[allow(dead_code)]
     ModifierSpan(usize, Modifiers, Vec<MarkdownSpan>),
-    Link(Link)
+    Link(Link),
+    Tag,
+    /// Inline code and fenced code blocks
+    Code
 }
 
 pub struct Link {
+    #[allow(dead_code)]
     pub display: Option<SimpleSpan>,
     pub target: SimpleSpan
 }
 
+impl Markdown {
+    pub fn parse(source: &str) -> Self {
+        let spans = markdown_parser()
+            .parse(source)
+            .into_output()
+            .unwrap_or_default();
+        let (frontmatter, frontmatter_tags) = frontmatter(source).unzip();
+        let untagged = frontmatter
+            .into_iter()
+            .chain(
+                lines(source)
+                    .filter(|(_, line)| is_heading(line))
+                    .map(|(start, line)| start..start + line.len())
+            )
+            .collect();
+        Self {
+            spans,
+            untagged,
+            frontmatter_tags: frontmatter_tags.unwrap_or_default()
+        }
+    }
+
+    /// Links in document order, including the ones inside bold or italic text
+    pub fn links(&self) -> Vec<&Link> {
+        let mut links = Vec::new();
+        walk(&self.spans, &mut |span| {
+            if let MarkdownSpanKind::Link(link) = &span.kind {
+                links.push(link);
+            }
+        });
+
+        links
+    }
+
+    /// Spans of the names of the frontmatter tags followed by the inline `#tags` in document
+    /// order, the `#` isn't included. Tags in code and headings are skipped
+    pub fn tags(&self) -> Vec<SimpleSpan> {
+        let mut tags = self.frontmatter_tags.clone();
+        walk(&self.spans, &mut |span| {
+            if let MarkdownSpanKind::Tag = span.kind
+                && !self
+                    .untagged
+                    .iter()
+                    .any(|untagged| untagged.contains(&span.span.start))
+            {
+                tags.push((span.span.start + 1..span.span.end).into());
+            }
+        });
+
+        tags
+    }
+}
+
+/// Lines along with their byte offsets, without the line endings
+fn lines(source: &str) -> impl Iterator<Item = (usize, &str)> {
+    source.split_inclusive('\n').scan(0, |start, line| {
+        let offset = *start;
+        *start += line.len();
+        Some((offset, line.trim_end_matches(['\n', '\r'])))
+    })
+}
+
+/// Whether a line is an ATX heading like `## Title`, `#tag` at the start of a line isn't one
+fn is_heading(line: &str) -> bool {
+    let line = line.trim_start_matches(' ');
+    let level = line.len() - line.trim_start_matches('#').len();
+    (1..=6).contains(&level) && line[level..].chars().next().is_none_or(char::is_whitespace)
+}
+
+/// The span of the frontmatter between `---` lines at the start of a note and the tags listed
+/// under its `tags` key, either as `[a, b]`, `a, b` or one `- a` per line
+fn frontmatter(source: &str) -> Option<(Range<usize>, Vec<SimpleSpan>)> {
+    let mut lines = lines(source);
+    if lines.next()?.1.trim_end() != "---" {
+        return None;
+    }
+
+    let mut tags = Vec::new();
+    let mut in_list = false;
+    for (start, line) in lines {
+        if matches!(line.trim_end(), "---" | "...") {
+            return Some((0..start + line.len(), tags));
+        }
+
+        if let Some(value) = line.strip_prefix("tags:") {
+            in_list = value.trim().is_empty();
+            tags.extend(names(value, start + "tags:".len()));
+        } else if in_list && let Some(item) = line.trim_start().strip_prefix('-') {
+            tags.extend(names(item, start + line.len() - item.len()));
+        } else if !line.starts_with(char::is_whitespace) {
+            in_list = false;
+        }
+    }
+
+    None
+}
+
+/// Spans of the words of a frontmatter value starting at an offset, without quotes, brackets or
+/// a leading `#`
+fn names(value: &str, offset: usize) -> impl Iterator<Item = SimpleSpan> {
+    value
+        .split(|ch: char| ch.is_whitespace() || matches!(ch, ',' | '[' | ']' | '"' | '\''))
+        .map(move |word| {
+            let start = offset + word.as_ptr() as usize - value.as_ptr() as usize;
+            let name = word.strip_prefix('#').unwrap_or(word);
+            start + word.len() - name.len()..start + word.len()
+        })
+        .filter(|name| !name.is_empty())
+        .map(Into::into)
+}
+
+fn walk<'a>(spans: &'a [MarkdownSpan], visit: &mut impl FnMut(&'a MarkdownSpan)) {
+    for span in spans {
+        visit(span);
+        if let MarkdownSpanKind::ModifierSpan(_, _, inner) = &span.kind {
+            walk(inner, visit);
+        }
+    }
+}
+
 bitflags! {
     #[derive(Default, Clone, Copy)]
     pub struct Modifiers: u8 {
@@ -41,27 +175,59 @@ bitflags! {
 fn markdown_parser<'a>() -> impl Parser<'a, &'a str, Vec<MarkdownSpan>, Context<ParsingContext>> {
     let link_display = just("|")
         .ignore_then(
-            none_of("|")
-                .then(any().repeated().exactly(1))
+            choice((just("]]"), just("\n")))
+                .not()
+                .then(any())
                 .repeated()
                 .map_with(|_, extra| extra.span())
-                .or_not()
         )
+        .or_not()
         .boxed();
     let link = just("[[")
         .ignore_then(
-            choice((just("]]"), just("|")))
+            choice((just("]]"), just("|"), just("\n")))
                 .not()
-                .then(any().repeated().exactly(1))
+                .then(any())
                 .repeated()
                 .map_with(|_, extra| extra.span())
                 .then(link_display)
         )
+        .then_ignore(just("]]"))
         .map_with(|(target, display), extra| MarkdownSpan {
             kind: MarkdownSpanKind::Link(Link { display, target }),
             span: extra.span()
         })
         .boxed();
+    // Purely numeric tags like `#1` are usually issue numbers
+    let tag = just("#")
+        .ignore_then(
+            any()
+                .filter(|ch: &char| ch.is_alphanumeric() || matches!(ch, '-' | '_' | '/'))
+                .repeated()
+                .at_least(1)
+                .to_slice()
+                .filter(|name: &&str| !name.chars().all(char::is_numeric))
+        )
+        .map_with(|_, extra| MarkdownSpan {
+            kind: MarkdownSpanKind::Tag,
+            span: extra.span()
+        })
+        .boxed();
+    let code = choice((
+        just("```")
+            .then(any().and_is(just("```").not()).repeated())
+            .then(just("```"))
+            .ignored(),
+        just("`")
+            .then(none_of("`").repeated())
+            .then(just("`"))
+            .ignored()
+    ))
+    .map_with(|_, extra| MarkdownSpan {
+        kind: MarkdownSpanKind::Code,
+        span: extra.span()
+    })
+    .boxed();
     let escaped_char = just("\\")
         .then(any().repeated().exactly(1))
         .map_with(|_, extra| MarkdownSpan {
@@ -71,6 +237,7 @@ fn markdown_parser<'a>() -> impl Parser<'a, &'a str, Vec<MarkdownSpan>, Context<
         .boxed();
     recursive(|markdown| {
         let non_text = choice((
+            code,
             modifier_span(
                 markdown.clone(),
                 "***",
@@ -108,6 +275,7 @@ fn markdown_parser<'a>() -> impl Parser<'a, &'a str, Vec<MarkdownSpan>, Context<
                 ParsingContext::UNDERSCORE
             ),
             link,
+            tag,
             escaped_char
         ))
         .boxed();
@@ -115,6 +283,8 @@ fn markdown_parser<'a>() -> impl Parser<'a, &'a str, Vec<MarkdownSpan>, Context<
             .clone()
             .not()
             .then(choice((
+                // A `#` right after a word like `issue#2` or `https://a.b/#c` starts no tag
+                none_of(" \t\r\n").then(just("#")).ignored().boxed(),
                 just("*")
                     .contextual()
                     .configure(|_, ctx: &ParsingContext| !ctx.contains(ParsingContext::ASTERISK))
@@ -134,7 +304,7 @@ fn markdown_parser<'a>() -> impl Parser<'a, &'a str, Vec<MarkdownSpan>, Context<
             .repeated()
             .at_least(1)
             .map_with(|_, extra| MarkdownSpan {
-                kind: MarkdownSpanKind::EscapedChar,
+                kind: MarkdownSpanKind::Text,
                 span: extra.span()
             })
             .boxed();
@@ -1,17 +1,40 @@
 use iced::{
-    Color,
-    keyboard::{Key, Modifiers, key::Named},
+    keyboard::{
+        Key, Modifiers,
+        key::{Named, NativeCode, Physical}
+    },
     widget::text_editor::{self, Binding, KeyPress, Motion}
 };
-use strum::AsRefStr;
+use serde::{Deserialize, Serialize};
+use strum::{AsRefStr, EnumIter};
 
-use crate::{CATPPUCCIN_MOCHA, Message, command::Command};
+use crate::{Message, command::Command, explorer};
 
-#[derive(Clone, Copy, AsRefStr)]
+#[derive(Clone, Copy, AsRefStr, EnumIter, Serialize, Deserialize)]
 pub enum Mode {
     Normal,
     Select,
-    Insert
+    Insert,
+    /// Keys act on the file explorer instead of the buffer
+    Panel
+}
+
+/// A key that picks what the next key does
+#[derive(Clone, Copy, AsRefStr, EnumIter)]
+pub enum Prefix {
+    #[strum(serialize = "Ctrl-w")]
+    Window,
+    #[strum(serialize = "g")]
+    Go
+}
+
+impl Prefix {
+    pub fn bindings(&self) -> fn(KeyPress) -> Option<Binding<Message>> {
+        match self {
+            Prefix::Window => window,
+            Prefix::Go => go
+        }
+    }
 }
 
 impl Mode {
@@ -19,17 +42,146 @@ impl Mode {
         match self {
             Mode::Normal => normal,
             Mode::Select => select,
-            Mode::Insert => insert
+            Mode::Insert => insert,
+            Mode::Panel => panel
         }
     }
 
-    pub fn color(&self) -> Color {
-        match self {
-            Mode::Normal => CATPPUCCIN_MOCHA.mauve,
-            Mode::Select => CATPPUCCIN_MOCHA.flamingo,
-            Mode::Insert => CATPPUCCIN_MOCHA.green
+    pub fn keymap(&self) -> Vec<(String, String)> {
+        keymap(self.bindings())
+    }
+}
+
+/// Lists keybindings by probing a binding function with every key
+pub fn keymap(bindings: fn(KeyPress) -> Option<Binding<Message>>) -> Vec<(String, String)> {
+    const NAMED: [Named; 14] = [
+        Named::Escape,
+        Named::Enter,
+        Named::Tab,
+        Named::Space,
+        Named::Backspace,
+        Named::Delete,
+        Named::Home,
+        Named::End,
+        Named::PageUp,
+        Named::PageDown,
+        Named::ArrowLeft,
+        Named::ArrowRight,
+        Named::ArrowUp,
+        Named::ArrowDown
+    ];
+    const MODIFIERS: [Modifiers; 6] = [
+        Modifiers::NONE,
+        Modifiers::SHIFT,
+        Modifiers::CTRL,
+        Modifiers::ALT,
+        Modifiers::CTRL.union(Modifiers::SHIFT),
+        Modifiers::ALT.union(Modifiers::SHIFT)
+    ];
+
+    let keys = (b'!'..=b'~')
+        .map(|ch| Key::Character(char::from(ch).to_string().into()))
+        .chain(NAMED.map(Key::Named));
+    let mut keymap = Vec::new();
+    let mut inserts_text = false;
+    let mut cancels = false;
+    for key in keys {
+        for modifiers in MODIFIERS {
+            let press = KeyPress {
+                key: key.clone(),
+                modified_key: key.clone(),
+                physical_key: Physical::Unidentified(NativeCode::Unidentified),
+                modifiers,
+                text: None,
+                status: text_editor::Status::Focused { is_hovered: false }
+            };
+
+            match bindings(press) {
+                Some(Binding::Insert(_) | Binding::Sequence(_)) => inserts_text = true,
+                Some(Binding::Custom(Message::UnboundKey(_))) => cancels = true,
+                Some(binding) => keymap.push((describe_key(&key, modifiers), describe(&binding))),
+                None => {}
+            }
+        }
+    }
+
+    if inserts_text {
+        keymap.push(("<other>".to_string(), "Insert the typed text".to_string()));
+    }
+
+    if cancels {
+        keymap.push(("<other>".to_string(), "Stop waiting for a key".to_string()));
+    }
+
+    keymap
+}
+
+fn describe_key(key: &Key, modifiers: Modifiers) -> String {
+    let mut description = String::new();
+    for (modifier, name) in [
+        (Modifiers::CTRL, "Ctrl-"),
+        (Modifiers::ALT, "Alt-"),
+        (Modifiers::SHIFT, "Shift-")
+    ] {
+        if modifiers.contains(modifier) {
+            description.push_str(name);
+        }
+    }
+
+    match key {
+        Key::Character(ch) => description.push_str(ch),
+        Key::Named(named) => description.push_str(&format!("{named:?}")),
+        Key::Unidentified => description.push('?')
+    }
+
+    description
+}
+
+fn describe(binding: &Binding<Message>) -> String {
+    match binding {
+        Binding::Unfocus => "Unfocus the editor".to_string(),
+        Binding::Copy => "Copy the selection".to_string(),
+        Binding::Cut => "Cut the selection".to_string(),
+        Binding::Paste => "Paste".to_string(),
+        Binding::Move(motion) => format!("Move {}", describe_motion(*motion)),
+        Binding::Select(motion) => format!("Extend the selection {}", describe_motion(*motion)),
+        Binding::SelectWord => "Select the word".to_string(),
+        Binding::SelectLine => "Select the line".to_string(),
+        Binding::SelectAll => "Select everything".to_string(),
+        Binding::Insert(ch) => format!("Insert {ch:?}"),
+        Binding::Enter => "Insert a line break".to_string(),
+        Binding::Backspace => "Delete the previous character".to_string(),
+        Binding::Delete => "Delete the next character or the selection".to_string(),
+        Binding::Sequence(bindings) => bindings.iter().map(describe).collect::<Vec<_>>().join(", "),
+        Binding::Custom(Message::EnterCommandMode) => "Enter command mode".to_string(),
+        Binding::Custom(Message::EnterCommandModeWith(command)) => {
+            format!("Enter command mode with `:{command}`")
+        }
+        Binding::Custom(Message::SwitchMode(mode)) => format!("Switch to {} mode", mode.as_ref()),
+        Binding::Custom(Message::Command(command)) => format!("`:{command}`"),
+        Binding::Custom(Message::Explorer(message)) => message.description().to_string(),
+        Binding::Custom(Message::Prefix(Some(prefix))) => {
+            format!("Wait for the key after `{}`", prefix.as_ref())
         }
-        .into()
+        Binding::Custom(Message::Prefix(None)) => "Stop waiting for a key".to_string(),
+        Binding::Custom(_) => "Custom action".to_string()
+    }
+}
+
+fn describe_motion(motion: Motion) -> &'static str {
+    match motion {
+        Motion::Left => "left",
+        Motion::Right => "right",
+        Motion::Up => "up",
+        Motion::Down => "down",
+        Motion::WordLeft => "a word left",
+        Motion::WordRight => "a word right",
+        Motion::Home => "to the start of the line",
+        Motion::End => "to the end of the line",
+        Motion::PageUp => "a page up",
+        Motion::PageDown => "a page down",
+        Motion::DocumentStart => "to the start of the document",
+        Motion::DocumentEnd => "to the end of the document"
     }
 }
 
@@ -43,6 +195,10 @@ fn universal(key: Key<&str>, modifiers: Modifiers) -> Option<Binding<Message>> {
         (K::Character("v"), M::CTRL) => B::Paste,
         (K::Character("x"), M::CTRL) => B::Cut,
         (K::Character("s"), M::CTRL) => B::Custom(Message::Command(Command::Write(None))),
+        (K::Character("=" | "+"), M::CTRL) => B::Custom(Message::Command(Command::ZoomIn)),
+        (K::Character("-"), M::CTRL) => B::Custom(Message::Command(Command::ZoomOut)),
+        (K::Character("0"), M::CTRL) => B::Custom(Message::Command(Command::ZoomReset)),
+        (K::Character("w"), M::CTRL) => B::Custom(Message::Prefix(Some(Prefix::Window))),
         (K::Character("a"), M::NONE) => B::SelectAll,
         (K::Named(N::PageDown), M::NONE) => B::Move(Motion::PageDown),
         (K::Named(N::PageUp), M::NONE) => B::Move(Motion::PageUp),
@@ -82,6 +238,11 @@ fn normal(key: KeyPress) -> Option<Binding<Message>> {
     use Modifiers as M;
     let binding = match (key.as_ref(), modifiers) {
         (K::Character(";"), M::SHIFT) => B::Custom(Message::EnterCommandMode),
+        (K::Character("\\"), M::SHIFT) => B::Custom(Message::EnterCommandModeWith("pipe ")),
+        (K::Character("1"), M::SHIFT) => B::Custom(Message::EnterCommandModeWith("insert-output ")),
+        (K::Character("1"), _) if modifiers == M::SHIFT | M::ALT => {
+            B::Custom(Message::EnterCommandModeWith("append-output "))
+        }
         (K::Character("i"), M::NONE) => B::Custom(Message::SwitchMode(Mode::Insert)),
         (K::Character("v"), M::NONE) => B::Custom(Message::SwitchMode(Mode::Select)),
         (K::Character("h"), M::NONE) => B::Move(Motion::Left),
@@ -95,6 +256,8 @@ fn normal(key: KeyPress) -> Option<Binding<Message>> {
         (K::Character("p"), M::NONE) => B::Paste,
         (K::Character("%"), M::NONE) => B::SelectAll,
         (K::Character("x"), M::NONE) => B::SelectLine,
+        (K::Character("e"), M::NONE) => B::Custom(Message::Command(Command::ExplorerFocus)),
+        (K::Character("g"), M::NONE) => B::Custom(Message::Prefix(Some(Prefix::Go))),
         (key, _) => return universal(key, modifiers)
     };
 
@@ -152,6 +315,11 @@ fn select(key: KeyPress) -> Option<Binding<Message>> {
     use Named as N;
     let binding = match (key.as_ref(), modifiers) {
         (K::Character(";"), M::SHIFT) => B::Custom(Message::EnterCommandMode),
+        (K::Character("\\"), M::SHIFT) => B::Custom(Message::EnterCommandModeWith("pipe ")),
+        (K::Character("1"), M::SHIFT) => B::Custom(Message::EnterCommandModeWith("insert-output ")),
+        (K::Character("1"), _) if modifiers == M::SHIFT | M::ALT => {
+            B::Custom(Message::EnterCommandModeWith("append-output "))
+        }
         (K::Named(N::Escape), M::NONE) => B::Custom(Message::SwitchMode(Mode::Normal)),
         (K::Character("i"), M::NONE) => B::Custom(Message::SwitchMode(Mode::Insert)),
         (K::Character("h") | K::Named(N::ArrowLeft), M::NONE) => B::Select(Motion::Left),
@@ -170,3 +338,100 @@ fn select(key: KeyPress) -> Option<Binding<Message>> {
 
     Some(binding)
 }
+
+fn panel(key: KeyPress) -> Option<Binding<Message>> {
+    if key.status == text_editor::Status::Active {
+        return None;
+    }
+
+    let KeyPress { key, modifiers, .. } = key;
+
+    use Binding as B;
+    use Key as K;
+    use Modifiers as M;
+    use Named as N;
+    let explorer = |message| B::Custom(Message::Explorer(message));
+    let binding = match (key.as_ref(), modifiers) {
+        (K::Character(";"), M::SHIFT) => B::Custom(Message::EnterCommandMode),
+        (K::Named(N::Escape) | K::Character("e"), M::NONE) => {
+            B::Custom(Message::SwitchMode(Mode::Normal))
+        }
+        (K::Character("q"), M::NONE) => B::Custom(Message::Command(Command::Explorer)),
+        (K::Character("j") | K::Named(N::ArrowDown), M::NONE) => explorer(explorer::Message::Down),
+        (K::Character("k") | K::Named(N::ArrowUp), M::NONE) => explorer(explorer::Message::Up),
+        (K::Character("l") | K::Named(N::ArrowRight | N::Enter), M::NONE) => {
+            explorer(explorer::Message::Expand)
+        }
+        (K::Character("h") | K::Named(N::ArrowLeft), M::NONE) => {
+            explorer(explorer::Message::Collapse)
+        }
+        (K::Character("a"), M::NONE) => explorer(explorer::Message::Create),
+        (K::Character("r"), M::NONE) => explorer(explorer::Message::Rename),
+        (K::Character("d"), M::NONE) => explorer(explorer::Message::Trash),
+        // Editing keys would change the buffer behind the explorer
+        _ => return None
+    };
+
+    Some(binding)
+}
+
+fn window(key: KeyPress) -> Option<Binding<Message>> {
+    if key.status == text_editor::Status::Active {
+        return None;
+    }
+
+    let KeyPress { key, modifiers, .. } = key;
+
+    use Binding as B;
+    use Key as K;
+    use Modifiers as M;
+    use Named as N;
+    let command = match (key.as_ref(), modifiers) {
+        (K::Named(N::Escape), M::NONE) => {
+            return Some(B::Custom(Message::Prefix(None)));
+        }
+        (K::Character("h") | K::Named(N::ArrowLeft), M::NONE) => Command::WindowLeft,
+        (K::Character("j") | K::Named(N::ArrowDown), M::NONE) => Command::WindowDown,
+        (K::Character("k") | K::Named(N::ArrowUp), M::NONE) => Command::WindowUp,
+        (K::Character("l") | K::Named(N::ArrowRight), M::NONE) => Command::WindowRight,
+        (K::Character("w"), M::NONE | M::CTRL) => Command::WindowNext,
+        (K::Character("v"), M::NONE) => Command::VerticalSplit,
+        (K::Character("s"), M::NONE) => Command::HorizontalSplit,
+        (K::Character("q" | "c"), M::NONE) => Command::WindowClose,
+        (K::Character("o"), M::NONE) => Command::WindowOnly,
+        _ => return unbound(&key, modifiers)
+    };
+
+    Some(B::Custom(Message::Command(command)))
+}
+
+fn go(key: KeyPress) -> Option<Binding<Message>> {
+    if key.status == text_editor::Status::Active {
+        return None;
+    }
+
+    let KeyPress { key, modifiers, .. } = key;
+
+    use Binding as B;
+    use Key as K;
+    use Modifiers as M;
+    use Named as N;
+    let binding = match (key.as_ref(), modifiers) {
+        (K::Named(N::Escape), M::NONE) => B::Custom(Message::Prefix(None)),
+        (K::Character("t"), M::NONE) => B::Custom(Message::Command(Command::TabNext)),
+        (K::Character("t"), M::SHIFT) => B::Custom(Message::Command(Command::TabPrevious)),
+        _ => return unbound(&key, modifiers)
+    };
+
+    Some(binding)
+}
+
+/// A key without a binding after a prefix stops waiting, modifiers alone belong to the next key
+fn unbound(key: &Key, modifiers: Modifiers) -> Option<Binding<Message>> {
+    match key {
+        Key::Named(Named::Shift | Named::Control | Named::Alt | Named::Super) => None,
+        key => Some(Binding::Custom(Message::UnboundKey(describe_key(
+            key, modifiers
+        ))))
+    }
+}
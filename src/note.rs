@@ -0,0 +1,61 @@
+use std::fs;
+
+use crate::{PathBuf, config::NewNoteConfig, error::Result};
+
+/// Turns a title into a file name friendly slug, `My Great Note!` becomes `my-great-note`
+pub fn slugify(title: &str) -> String {
+    let mut slug = String::new();
+    for ch in title.chars() {
+        if ch.is_alphanumeric() {
+            slug.extend(ch.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.truncate(slug.trim_end_matches('-').len());
+    slug
+}
+
+/// Creates a note from a title in the configured folder, filling it from the template.
+///
+/// A note that already exists is left as is, notes without a title get a fresh `untitled` name
+pub fn create(config: &NewNoteConfig, extension: &str, title: Option<&str>) -> Result<PathBuf> {
+    fs::create_dir_all(&config.folder)?;
+    let slug = title.map(slugify).filter(|slug| !slug.is_empty());
+    let path = match &slug {
+        Some(slug) => config.folder.join(format!("{slug}.{extension}")),
+        None => (0..)
+            .map(|n| match n {
+                0 => config.folder.join(format!("untitled.{extension}")),
+                n => config.folder.join(format!("untitled-{n}.{extension}"))
+            })
+            .find(|path| !path.exists())
+            .expect("there are only finitely many files")
+    };
+
+    if path.exists() {
+        return Ok(path);
+    }
+
+    let content = match &config.template {
+        Some(template) => fs::read_to_string(template)?.replace("{{title}}", title.unwrap_or("")),
+        None => String::new()
+    };
+
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::note::slugify;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("My Great Note!"), "my-great-note");
+        assert_eq!(slugify("  Příliš   žluťoučký kůň "), "příliš-žluťoučký-kůň");
+        assert_eq!(slugify("2025/10 -- Meeting notes"), "2025-10-meeting-notes");
+        assert_eq!(slugify("?!"), "");
+    }
+}
@@ -0,0 +1,73 @@
+use std::{fs, io::ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Path, PathBuf, atomic,
+    cli::Location,
+    error::{Error, Result},
+    mode::Mode
+};
+
+const PATH: &str = ".pokisona/session.json";
+
+/// The open files of a vault, restored on the next launch
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    /// Open files with their cursor positions
+    pub buffers: Vec<Location>,
+    pub current: Option<PathBuf>,
+    pub mode: Mode,
+    /// The pane layout of every tab
+    pub tabs: Vec<Layout>,
+    pub tab: usize,
+    pub explorer: Explorer
+}
+
+/// A pane or a split of two layouts
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    Split {
+        vertical: bool,
+        ratio: f32,
+        a: Box<Layout>,
+        b: Box<Layout>
+    },
+    Pane(Pane)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Pane {
+    /// The shown file with the cursor of the pane, none for buffers that aren't files
+    pub location: Option<Location>,
+    /// Lines scrolled down from the top
+    pub scroll: usize,
+    pub focused: bool
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Explorer {
+    pub visible: bool,
+    pub expanded: Vec<PathBuf>,
+    pub selected: Option<PathBuf>
+}
+
+impl Session {
+    pub fn load() -> Result<Option<Self>> {
+        match fs::read_to_string(PATH) {
+            Ok(session) => serde_json::from_str(&session)
+                .map(Some)
+                .map_err(|error| Error::InvalidSession(error.to_string())),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into())
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let session = serde_json::to_string_pretty(self)
+            .map_err(|error| Error::InvalidSession(error.to_string()))?;
+        atomic::write(Path::new(PATH), &session, false)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use std::{io::ErrorKind, process::Stdio};
+
+use iced::widget::text_editor::Position;
+use smol::{future, io::AsyncWriteExt, process};
+
+use crate::error::{Error, Result};
+
+/// Where the output of a shell command ends up
+#[derive(Clone, Copy, Debug)]
+pub enum Output {
+    Scratch,
+    Replace,
+    Insert,
+    Append
+}
+
+/// The text of a buffer the output replaces, captured when the command starts since the user
+/// can switch buffers and move the cursor while it runs
+#[derive(Clone, Copy, Debug)]
+pub struct Target {
+    /// The id of the buffer
+    pub buffer: usize,
+    pub start: Position,
+    pub end: Position
+}
+
+/// Runs a command with `sh -c`, feeding it input if given.
+///
+/// The working directory of pokisona is the vault root, so that's where the command runs
+pub async fn run(command: String, input: Option<String>) -> Result<String> {
+    let mut child = process::Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let stdin = child.stdin.take();
+    let write = async move {
+        if let (Some(mut stdin), Some(input)) = (stdin, input) {
+            stdin.write_all(input.as_bytes()).await?;
+        }
+
+        Ok::<_, std::io::Error>(())
+    };
+
+    let (written, output) = future::zip(write, child.output()).await;
+    let output = output?;
+    match written {
+        // The command doesn't have to read its input
+        Err(error) if error.kind() != ErrorKind::BrokenPipe => return Err(error.into()),
+        _ => {}
+    }
+
+    if !output.status.success() {
+        return Err(Error::ShellCommandFailed {
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string()
+        });
+    }
+
+    String::from_utf8(output.stdout).map_err(|_| Error::ShellOutputNotUtf8)
+}
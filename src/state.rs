@@ -0,0 +1,39 @@
+use std::{fs, io::ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::SCALE_RANGE,
+    error::{Error, Result}
+};
+
+const PATH: &str = ".pokisona/state.toml";
+
+/// Ui state remembered per vault
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct State {
+    pub scale: Option<f32>
+}
+
+impl State {
+    pub fn load() -> Result<Self> {
+        let mut state: Self = match fs::read_to_string(PATH) {
+            Ok(state) => toml::from_str(&state)
+                .map_err(|error| Error::InvalidState(error.message().to_string()))?,
+            Err(error) if error.kind() == ErrorKind::NotFound => Self::default(),
+            Err(error) => return Err(error.into())
+        };
+
+        // A scale edited by hand falls back to the one from the config
+        state.scale = state.scale.filter(|scale| SCALE_RANGE.contains(scale));
+        Ok(state)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let state =
+            toml::to_string(self).map_err(|error| Error::InvalidState(error.to_string()))?;
+        fs::write(PATH, state)?;
+        Ok(())
+    }
+}
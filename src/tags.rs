@@ -0,0 +1,161 @@
+use iced::{
+    Length,
+    keyboard::{self, Key, key::Named},
+    padding,
+    widget::{button, column, container, row, scrollable, text}
+};
+
+use crate::{
+    Element, Path, PathBuf,
+    error::{Error, Result},
+    index::Index,
+    theme::Palette
+};
+
+/// Lists the tags of the vault, choosing one lists the notes carrying it or one of its children
+pub struct Picker {
+    /// Tags along with their notes, children follow their parents
+    tags: Vec<(String, Vec<PathBuf>)>,
+    selected: usize,
+    /// The note of the selected tag Enter opens, none while the keys move through the tags
+    note: Option<usize>
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Select(usize),
+    Up,
+    Down,
+    /// Moves from the tags to their notes
+    Right,
+    /// Moves from the notes back to the tags
+    Left,
+    /// Opens the selected note
+    Enter,
+    Open(PathBuf),
+    Close
+}
+
+impl Picker {
+    const SPACING: f32 = 10.0;
+    const LIST_WIDTH: f32 = 200.0;
+    const INDENT: f32 = 16.0;
+
+    pub fn new(index: &Index) -> Result<Self> {
+        let mut tags: Vec<_> = index
+            .tag_tree()
+            .into_iter()
+            .map(|tag| {
+                let notes = index.tagged(tag).into_iter().map(Path::to_path_buf);
+                (tag.to_string(), notes.collect())
+            })
+            .collect();
+        if tags.is_empty() {
+            return Err(Error::NoTags);
+        }
+
+        // Compared by parts, `a-b` would land between `a` and `a/b` otherwise
+        tags.sort_by(|(a, _), (b, _)| a.split('/').cmp(b.split('/')));
+        Ok(Self {
+            tags,
+            selected: 0,
+            note: None
+        })
+    }
+
+    /// The message of a key pressed while the picker is open
+    pub fn key(event: keyboard::Event) -> Option<Message> {
+        let keyboard::Event::KeyPressed { key, .. } = event else {
+            return None;
+        };
+
+        match key.as_ref() {
+            Key::Named(Named::ArrowUp) | Key::Character("k") => Some(Message::Up),
+            Key::Named(Named::ArrowDown) | Key::Character("j") => Some(Message::Down),
+            Key::Named(Named::ArrowRight) | Key::Character("l") => Some(Message::Right),
+            Key::Named(Named::ArrowLeft) | Key::Character("h") => Some(Message::Left),
+            Key::Named(Named::Enter) => Some(Message::Enter),
+            Key::Named(Named::Escape) | Key::Character("q") => Some(Message::Close),
+            _ => None
+        }
+    }
+
+    /// Handles the messages that only move the selection, returns the note to open if any
+    pub fn update(&mut self, message: Message) -> Option<PathBuf> {
+        let notes = &self.tags[self.selected].1;
+        let step = |index: usize, offset: isize, len: usize| {
+            index.saturating_add_signed(offset).min(len - 1)
+        };
+        match (message, self.note) {
+            (Message::Select(index), _) => {
+                self.selected = index;
+                self.note = None;
+            }
+            (Message::Up, None) => self.selected = step(self.selected, -1, self.tags.len()),
+            (Message::Down, None) => self.selected = step(self.selected, 1, self.tags.len()),
+            (Message::Up, Some(note)) => self.note = Some(step(note, -1, notes.len())),
+            (Message::Down, Some(note)) => self.note = Some(step(note, 1, notes.len())),
+            (Message::Right | Message::Enter, None) if !notes.is_empty() => self.note = Some(0),
+            (Message::Left, Some(_)) => self.note = None,
+            (Message::Enter, Some(note)) => return notes.get(note).cloned(),
+            (Message::Open(path), _) => return Some(path),
+            _ => {}
+        }
+
+        None
+    }
+
+    pub fn view(&self, palette: &Palette) -> Element<'_, Message> {
+        let tags = scrollable(column(self.tags.iter().enumerate().map(
+            |(index, (tag, _))| {
+                let (depth, name) = match tag.rsplit_once('/') {
+                    Some((_, name)) => (tag.matches('/').count(), name),
+                    None => (0, tag.as_str())
+                };
+                let tag = button(text(format!("#{name}")))
+                    .on_press(Message::Select(index))
+                    .style(if index == self.selected {
+                        button::primary
+                    } else {
+                        button::text
+                    })
+                    .width(Length::Fill);
+                container(tag)
+                    .padding(padding::left(depth as f32 * Self::INDENT))
+                    .into()
+            }
+        )))
+        .width(Self::LIST_WIDTH);
+
+        let (tag, notes) = &self.tags[self.selected];
+        let notes = scrollable(column(notes.iter().enumerate().map(|(index, path)| {
+            button(text(path.as_str()))
+                .on_press(Message::Open(path.clone()))
+                .style(if self.note == Some(index) {
+                    button::primary
+                } else {
+                    button::text
+                })
+                .width(Length::Fill)
+                .into()
+        })))
+        .width(Length::Fill);
+        let notes = column![
+            text(format!("Notes tagged #{tag} or its children")).color(palette.subtext),
+            notes
+        ]
+        .spacing(Self::SPACING);
+
+        let picker = column![
+            text("Tags").size(24),
+            row![tags, notes]
+                .spacing(Self::SPACING)
+                .height(Length::Fill),
+            button("Cancel")
+                .on_press(Message::Close)
+                .style(button::secondary)
+        ]
+        .spacing(Self::SPACING);
+        container(picker).padding(Self::SPACING).into()
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use catppuccin::{FlavorColors, PALETTE};
+use iced::Color;
+use serde::{Deserialize, Deserializer, de};
+
+use crate::{
+    config::HighlighterTheme,
+    error::{Error, Result},
+    mode::Mode
+};
+
+pub const BUILTIN: [&str; 4] = ["latte", "frappe", "macchiato", "mocha"];
+
+/// Colors of the whole ui, user themes are defined in the config under `[themes.<name>]`
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Palette {
+    #[serde(deserialize_with = "color")]
+    pub background: Color,
+    #[serde(deserialize_with = "color")]
+    pub text: Color,
+    /// Dimmed text, like the names of buffers that aren't files
+    #[serde(deserialize_with = "color")]
+    pub subtext: Color,
+    /// Pressed buttons
+    #[serde(deserialize_with = "color")]
+    pub overlay: Color,
+    /// Background of the status bar
+    #[serde(deserialize_with = "color")]
+    pub bar: Color,
+    /// Selected text, defaults to a translucent mode color
+    #[serde(default, deserialize_with = "optional_color")]
+    pub selection: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub normal: Color,
+    #[serde(deserialize_with = "color")]
+    pub select: Color,
+    #[serde(deserialize_with = "color")]
+    pub insert: Color,
+    /// Panel mode, defaults to the normal mode color
+    #[serde(default, deserialize_with = "optional_color")]
+    pub panel: Option<Color>,
+    /// `#tags` in notes, defaults to the normal mode color
+    #[serde(default, deserialize_with = "optional_color")]
+    pub tag: Option<Color>,
+    #[serde(deserialize_with = "color")]
+    pub success: Color,
+    #[serde(deserialize_with = "color")]
+    pub warning: Color,
+    #[serde(deserialize_with = "color")]
+    pub danger: Color,
+    pub syntax: Syntax
+}
+
+/// Syntax highlighting, a preset like `"base16-mocha"` or a table of token colors
+#[derive(Clone, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum Syntax {
+    Preset(HighlighterTheme),
+    Colors(SyntaxColors)
+}
+
+/// Colors of the kinds of tokens, the missing ones keep the text color
+#[derive(Clone, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyntaxColors {
+    #[serde(deserialize_with = "optional_color")]
+    pub comment: Option<Color>,
+    #[serde(deserialize_with = "optional_color")]
+    pub string: Option<Color>,
+    /// Numbers and other literals
+    #[serde(deserialize_with = "optional_color")]
+    pub constant: Option<Color>,
+    #[serde(deserialize_with = "optional_color")]
+    pub keyword: Option<Color>,
+    #[serde(deserialize_with = "optional_color")]
+    pub function: Option<Color>,
+    #[serde(rename = "type", deserialize_with = "optional_color")]
+    pub kind: Option<Color>,
+    /// Markdown headings
+    #[serde(deserialize_with = "optional_color")]
+    pub heading: Option<Color>,
+    /// Markdown links
+    #[serde(deserialize_with = "optional_color")]
+    pub link: Option<Color>,
+    /// Markdown inline code and code blocks
+    #[serde(deserialize_with = "optional_color")]
+    pub code: Option<Color>
+}
+
+impl Palette {
+    const SELECTION_ALPHA: f32 = 0.5;
+
+    fn catppuccin(colors: FlavorColors) -> Self {
+        Self {
+            background: colors.base.into(),
+            text: colors.text.into(),
+            subtext: colors.subtext0.into(),
+            overlay: colors.overlay0.into(),
+            bar: colors.crust.into(),
+            selection: None,
+            normal: colors.mauve.into(),
+            select: colors.flamingo.into(),
+            insert: colors.green.into(),
+            panel: Some(colors.blue.into()),
+            tag: Some(colors.teal.into()),
+            success: colors.green.into(),
+            warning: colors.yellow.into(),
+            danger: colors.red.into(),
+            syntax: Syntax::Colors(SyntaxColors {
+                comment: Some(colors.overlay2.into()),
+                string: Some(colors.green.into()),
+                constant: Some(colors.peach.into()),
+                keyword: Some(colors.mauve.into()),
+                function: Some(colors.blue.into()),
+                kind: Some(colors.yellow.into()),
+                heading: Some(colors.red.into()),
+                link: Some(colors.blue.into()),
+                code: Some(colors.flamingo.into())
+            })
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        let colors = match name {
+            "latte" => PALETTE.latte.colors,
+            "frappe" => PALETTE.frappe.colors,
+            "macchiato" => PALETTE.macchiato.colors,
+            "mocha" => PALETTE.mocha.colors,
+            _ => return None
+        };
+
+        Some(Self::catppuccin(colors))
+    }
+
+    /// Finds a theme by name, user themes shadow the built-in ones.
+    ///
+    /// `auto` is latte or mocha depending on whether the desktop is light or dark
+    pub fn resolve(name: &str, themes: &HashMap<String, Palette>, dark: bool) -> Result<Self> {
+        let name = match name {
+            "auto" if dark => "mocha",
+            "auto" => "latte",
+            name => name
+        };
+
+        themes
+            .get(name)
+            .cloned()
+            .or_else(|| Self::builtin(name))
+            .ok_or_else(|| Error::UnknownTheme(name.to_string()))
+    }
+
+    pub fn mode(&self, mode: Mode) -> Color {
+        match mode {
+            Mode::Normal => self.normal,
+            Mode::Select => self.select,
+            Mode::Insert => self.insert,
+            Mode::Panel => self.panel.unwrap_or(self.normal)
+        }
+    }
+
+    pub fn selection(&self, mode: Mode) -> Color {
+        self.selection
+            .unwrap_or_else(|| self.mode(mode).scale_alpha(Self::SELECTION_ALPHA))
+    }
+}
+
+/// Parses a `#rrggbb` or `#rrggbbaa` color
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+    let color = String::deserialize(deserializer)?;
+    color
+        .parse()
+        .map_err(|_| de::Error::custom(format!("invalid color {color:?}")))
+}
+
+fn optional_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Color>, D::Error> {
+    color(deserializer).map(Some)
+}
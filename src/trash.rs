@@ -0,0 +1,142 @@
+use std::{cmp::Reverse, fs, io::ErrorKind};
+
+use jiff::{Timestamp, tz::TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Path, PathBuf,
+    error::{Error, Result}
+};
+
+const FILES: &str = ".pokisona/trash/files";
+const INFO: &str = ".pokisona/trash/info";
+
+/// Metadata stored next to a trashed file
+#[derive(Serialize, Deserialize)]
+pub struct TrashInfo {
+    pub path: PathBuf,
+    pub deleted: Timestamp
+}
+
+pub struct TrashEntry {
+    /// Name of the file inside the trash, unique unlike the original file name
+    pub name: String,
+    pub info: TrashInfo
+}
+
+/// Moves a file into the vault trash, returning its name inside the trash
+pub fn trash(path: &Path) -> Result<String> {
+    fs::create_dir_all(FILES)?;
+    fs::create_dir_all(INFO)?;
+    let file_name = path.file_name().ok_or(Error::NoPathSet)?;
+    let name = (0..)
+        .map(|n| match (n, path.file_stem(), path.extension()) {
+            (0, ..) => file_name.to_string(),
+            (n, Some(stem), Some(extension)) => format!("{stem}-{n}.{extension}"),
+            (n, ..) => format!("{file_name}-{n}")
+        })
+        .find(|name| !Path::new(FILES).join(name).exists())
+        .expect("there are only finitely many files");
+
+    let info = TrashInfo {
+        path: path.to_path_buf(),
+        deleted: Timestamp::now()
+    };
+
+    let info = toml::to_string(&info).map_err(|_| Error::InvalidTrashInfo)?;
+    let trashed = Path::new(FILES).join(&name);
+    fs::rename(path, &trashed)?;
+    // A file in the trash without its info couldn't be restored, so it's put back
+    if let Err(error) = fs::write(info_path(&name), info) {
+        fs::rename(&trashed, path)?;
+        return Err(error.into());
+    }
+
+    Ok(name)
+}
+
+/// Lists the trash, most recently deleted first
+pub fn list() -> Result<Vec<TrashEntry>> {
+    let entries = match fs::read_dir(INFO) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into())
+    };
+
+    let mut trash = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let Some(name) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_suffix(".toml"))
+            .map(ToString::to_string)
+        else {
+            continue;
+        };
+
+        let info = toml::from_str(&fs::read_to_string(entry.path())?)
+            .map_err(|_| Error::InvalidTrashInfo)?;
+        trash.push(TrashEntry { name, info });
+    }
+
+    trash.sort_by_key(|entry| Reverse(entry.info.deleted));
+    Ok(trash)
+}
+
+/// Restores a file by its name in the trash or its original path, returning where it was restored
+pub fn restore(file: &str) -> Result<PathBuf> {
+    let TrashEntry { name, info } = list()?
+        .into_iter()
+        .find(|entry| entry.name == file || entry.info.path == file)
+        .ok_or(Error::NotInTrash)?;
+
+    if info.path.exists() {
+        return Err(Error::RestoreTargetExists);
+    }
+
+    if let Some(parent) = info.path.parent()
+        && parent != ""
+    {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::rename(Path::new(FILES).join(&name), &info.path)?;
+    fs::remove_file(info_path(&name))?;
+    Ok(info.path)
+}
+
+/// Permanently deletes everything in the trash
+pub fn empty() -> Result<()> {
+    for dir in [FILES, INFO] {
+        match fs::remove_dir_all(dir) {
+            Err(error) if error.kind() != ErrorKind::NotFound => return Err(error.into()),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Renders the trash as a markdown table
+pub fn render(trash: &[TrashEntry]) -> String {
+    if trash.is_empty() {
+        return "Trash is empty\n".to_string();
+    }
+
+    let mut table = "| Name | Original path | Deleted |\n|---|---|---|\n".to_string();
+    for TrashEntry { name, info } in trash {
+        let deleted = info.deleted.to_zoned(TimeZone::system());
+        table.push_str(&format!(
+            "| {name} | {} | {} |\n",
+            info.path,
+            deleted.strftime("%Y-%m-%d %H:%M")
+        ));
+    }
+
+    table
+}
+
+fn info_path(name: &str) -> PathBuf {
+    Path::new(INFO).join(format!("{name}.toml"))
+}
@@ -0,0 +1,322 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, ErrorKind}
+};
+
+use camino::Utf8Component;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Path, PathBuf,
+    error::{Error, Result}
+};
+
+/// The vaults known to pokisona.
+///
+/// Vaults either live in `<data dir>/pokisona/vaults/<name>` or anywhere on disk, in which case
+/// they are registered in `<data dir>/pokisona/vaults.toml`
+#[derive(Clone)]
+pub struct Vaults {
+    data_dir: PathBuf,
+    registry: Registry
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct Registry {
+    vaults: BTreeMap<String, PathBuf>
+}
+
+impl Vaults {
+    pub fn load() -> Result<Self> {
+        let data_dir = dirs::data_dir()
+            .and_then(|dir| PathBuf::try_from(dir).ok())
+            .ok_or(Error::NoDataDir)?
+            .join("pokisona");
+        Self::at(data_dir)
+    }
+
+    /// The vaults of a data dir other than the user's
+    pub fn at(data_dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&data_dir)?;
+        let registry = match fs::read_to_string(data_dir.join("vaults.toml")) {
+            Ok(registry) => toml::from_str(&registry)
+                .map_err(|error| Error::InvalidVaultRegistry(error.message().to_string()))?,
+            Err(error) if error.kind() == ErrorKind::NotFound => Registry::default(),
+            Err(error) => return Err(error.into())
+        };
+
+        Ok(Self { data_dir, registry })
+    }
+
+    fn save(&self) -> Result<()> {
+        let registry = toml::to_string(&self.registry)
+            .map_err(|error| Error::InvalidVaultRegistry(error.to_string()))?;
+        fs::write(self.data_dir.join("vaults.toml"), registry)?;
+        Ok(())
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        match self.registry.vaults.get(name) {
+            Some(path) => path.clone(),
+            None => self.data_dir.join("vaults").join(name)
+        }
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.path(name).is_dir()
+    }
+
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.registry.vaults.contains_key(name)
+    }
+
+    /// Name of the registered vault at the given absolute path
+    pub fn name_of(&self, path: &Path) -> Option<&str> {
+        self.registry
+            .vaults
+            .iter()
+            .find(|(_, vault)| *vault == path)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Names of all vaults, sorted
+    pub fn list(&self) -> Result<Vec<String>> {
+        let mut vaults: Vec<_> = self.registry.vaults.keys().cloned().collect();
+        let entries = match self.data_dir.join("vaults").read_dir_utf8() {
+            Ok(entries) => Some(entries),
+            Err(error) if error.kind() == ErrorKind::NotFound => None,
+            Err(error) => return Err(error.into())
+        };
+
+        for entry in entries.into_iter().flatten() {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                vaults.push(entry.file_name().to_string());
+            }
+        }
+
+        vaults.sort();
+        vaults.dedup();
+        Ok(vaults)
+    }
+
+    pub fn default(&self) -> Result<Option<String>> {
+        match fs::read_to_string(self.data_dir.join("default")) {
+            Ok(name) => Ok(Some(name.trim().to_string())),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into())
+        }
+    }
+
+    pub fn set_default(&self, name: &str) -> Result<()> {
+        if !self.exists(name) {
+            return Err(Error::VaultDoesntExist(name.to_string()));
+        }
+
+        fs::write(self.data_dir.join("default"), name)?;
+        Ok(())
+    }
+
+    /// Forgets the default vault if it's the given one
+    pub fn clear_default(&self, name: &str) -> Result<()> {
+        if self.default()?.as_deref() == Some(name) {
+            fs::remove_file(self.data_dir.join("default"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates a new empty vault in the data dir
+    pub fn create(&self, name: &str) -> Result<PathBuf> {
+        validate_name(name)?;
+        let path = self.path(name);
+        if path.exists() {
+            return Err(Error::VaultAlreadyExists(name.to_string()));
+        }
+
+        fs::create_dir_all(path.join(".pokisona"))?;
+        Ok(path)
+    }
+
+    /// Registers an existing folder as a vault
+    pub fn register(&mut self, name: &str, path: &Path) -> Result<()> {
+        validate_name(name)?;
+        if self.exists(name) || self.is_registered(name) {
+            return Err(Error::VaultAlreadyExists(name.to_string()));
+        }
+
+        let path = path.canonicalize_utf8()?;
+        if !path.is_dir() {
+            return Err(Error::VaultDoesntExist(path.to_string()));
+        }
+
+        fs::create_dir_all(path.join(".pokisona"))?;
+        self.registry.vaults.insert(name.to_string(), path);
+        self.save()
+    }
+
+    /// Finds the vault of a folder, registering it under the folder name if it isn't one yet
+    pub fn register_folder(&mut self, path: &Path) -> Result<String> {
+        let path = path.canonicalize_utf8()?;
+        if let Some(name) = self.name_of(&path) {
+            return Ok(name.to_string());
+        }
+
+        let name = path
+            .file_name()
+            .ok_or_else(|| Error::InvalidVaultName(path.to_string()))?
+            .to_string();
+        self.register(&name, &path)?;
+        Ok(name)
+    }
+
+    /// Finds the vault containing a file by looking for a `.pokisona` folder in its parents,
+    /// registering it if it's not a vault yet. Returns the vault name and the path inside it
+    pub fn containing(&mut self, file: &Path) -> Result<(String, PathBuf)> {
+        let root = file
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.join(".pokisona").is_dir())
+            .ok_or_else(|| Error::NotInVault(file.to_string()))?;
+        let path = file
+            .strip_prefix(root)
+            .expect("root is a parent of the file")
+            .to_path_buf();
+        let managed = self.data_dir.join("vaults").canonicalize_utf8().ok();
+        let name = match (root.parent(), root.file_name()) {
+            (Some(parent), Some(name)) if managed.as_deref() == Some(parent) => name.to_string(),
+            _ => self.register_folder(root)?
+        };
+
+        Ok((name, path))
+    }
+
+    /// Removes a registered vault from the registry, its files are kept
+    pub fn unregister(&mut self, name: &str) -> Result<PathBuf> {
+        let path = self
+            .registry
+            .vaults
+            .remove(name)
+            .ok_or_else(|| Error::VaultDoesntExist(name.to_string()))?;
+        self.save()?;
+        self.clear_default(name)?;
+        Ok(path)
+    }
+
+    /// Renames a vault, registered vaults keep their folder
+    pub fn rename(&mut self, old: &str, new: &str) -> Result<()> {
+        validate_name(old)?;
+        validate_name(new)?;
+        if !self.exists(old) {
+            return Err(Error::VaultDoesntExist(old.to_string()));
+        }
+
+        if self.exists(new) || self.is_registered(new) {
+            return Err(Error::VaultAlreadyExists(new.to_string()));
+        }
+
+        match self.registry.vaults.remove(old) {
+            Some(path) => {
+                self.registry.vaults.insert(new.to_string(), path);
+                self.save()?;
+            }
+            None => fs::rename(self.path(old), self.path(new))?
+        }
+
+        if self.default()?.as_deref() == Some(old) {
+            fs::write(self.data_dir.join("default"), new)?;
+        }
+
+        Ok(())
+    }
+
+    /// Makes a vault the working directory, vaults in the data dir are created if needed
+    pub fn enter(&self, name: &str) -> Result<()> {
+        let path = self.path(name);
+        if self.is_registered(name) {
+            if !path.is_dir() {
+                return Err(Error::VaultDoesntExist(name.to_string()));
+            }
+        } else {
+            validate_name(name)?;
+            fs::create_dir_all(&path)?;
+        }
+
+        env::set_current_dir(&path)?;
+        fs::create_dir_all(".pokisona")?;
+        Ok(())
+    }
+}
+
+/// Rejects names that aren't a single folder inside the vaults dir, like `..` or `a/b`
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(Error::InvalidVaultName(name.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Recursively lists the files of a vault, skipping hidden entries like `.pokisona` and `.git`
+pub fn files(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in dir.read_dir_utf8()? {
+            let entry = entry?;
+            if entry.file_name().starts_with('.') {
+                continue;
+            }
+
+            if entry.file_type()?.is_dir() {
+                dirs.push(entry.into_path());
+            } else {
+                files.push(entry.into_path());
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Whether a path relative to the vault root stays inside of it
+pub fn is_inside(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Utf8Component::Normal(_) | Utf8Component::CurDir))
+}
+
+/// Where a path ends up when a file or folder is moved, none if it isn't inside of it
+pub fn moved(path: &Path, old: &Path, new: &Path) -> Option<PathBuf> {
+    let rest = path.strip_prefix(old).ok()?;
+    Some(if rest.as_str().is_empty() {
+        new.to_path_buf()
+    } else {
+        new.join(rest)
+    })
+}
+
+pub fn is_note(path: &Path, extension: &str) -> bool {
+    path.extension() == Some(extension)
+}
+
+/// Total size in bytes of everything in a directory
+pub fn size(dir: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in dir.read_dir_utf8()? {
+        let entry = entry?;
+        let metadata = fs::symlink_metadata(entry.path())?;
+        size += if metadata.is_dir() {
+            self::size(entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
@@ -0,0 +1,170 @@
+use std::time::{Duration, Instant};
+
+use iced::{
+    futures::{SinkExt, Stream, channel::mpsc},
+    stream
+};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use smol::{Timer, future};
+
+use crate::{
+    Path, PathBuf,
+    config::Config,
+    error::{Error, Result}
+};
+
+type Events = smol::channel::Receiver<notify::Result<notify::Event>>;
+
+/// Starts watching the directories, the watcher stops when dropped
+fn watcher(dirs: &[(&Path, RecursiveMode)]) -> Result<(RecommendedWatcher, Events)> {
+    let (sender, receiver) = smol::channel::unbounded();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send_blocking(event);
+    })
+    .map_err(|error| Error::Watch(error.to_string()))?;
+    for (dir, mode) in dirs {
+        watcher
+            .watch(dir.as_std_path(), *mode)
+            .map_err(|error| Error::Watch(error.to_string()))?;
+    }
+
+    Ok((watcher, receiver))
+}
+
+/// How long changes are collected into one batch
+const BATCH_DELAY: Duration = Duration::from_millis(100);
+
+/// Reports batches of the vault relative paths of files changed by other programs, or by
+/// pokisona itself
+pub fn watch() -> impl Stream<Item = Result<Vec<PathBuf>>> {
+    stream::channel(64, |mut output: mpsc::Sender<_>| async move {
+        let root = match Path::new(".").canonicalize_utf8() {
+            Ok(root) => root,
+            Err(error) => {
+                let _ = output.send(Err(error.into())).await;
+                return;
+            }
+        };
+
+        // The watcher has to live as long as the stream
+        let (_watcher, receiver) = match watcher(&[(&root, RecursiveMode::Recursive)]) {
+            Ok(watcher) => watcher,
+            Err(error) => {
+                let _ = output.send(Err(error)).await;
+                return;
+            }
+        };
+
+        while let Ok(event) = receiver.recv().await {
+            // Checkouts and pulls change many files at once, so the events that arrive shortly
+            // after the first one are reported together
+            let deadline = Instant::now() + BATCH_DELAY;
+            let mut paths = Vec::new();
+            let mut next = Some(event);
+            while let Some(event) = next {
+                match event {
+                    Ok(event) if !event.kind.is_access() => {
+                        for path in event.paths {
+                            let Some(path) = PathBuf::try_from(path)
+                                .ok()
+                                .and_then(|path| Some(path.strip_prefix(&root).ok()?.to_path_buf()))
+                                .filter(|path| is_watched(path) && !paths.contains(path))
+                            else {
+                                continue;
+                            };
+
+                            paths.push(path);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(error) => {
+                        let _ = output.send(Err(Error::Watch(error.to_string()))).await;
+                    }
+                }
+
+                next = future::or(async { receiver.recv().await.ok() }, async {
+                    Timer::at(deadline).await;
+                    None
+                })
+                .await;
+            }
+
+            if !paths.is_empty() {
+                let _ = output.send(Ok(paths)).await;
+            }
+        }
+    })
+}
+
+/// Reports when one of the config files of the vault changes. Only the directories of the files
+/// that exist are watched, a global config directory created later is noticed after a restart
+pub fn watch_config() -> impl Stream<Item = Result<()>> {
+    stream::channel(1, |mut output: mpsc::Sender<_>| async move {
+        // Editors often replace files instead of writing them, which the directories notice
+        let paths: Vec<_> = Config::paths(Some(Path::new(".")))
+            .into_iter()
+            .filter_map(|path| {
+                let dir = path.parent()?.canonicalize_utf8().ok()?;
+                Some(dir.join(path.file_name()?))
+            })
+            .collect();
+        let dirs: Vec<_> = paths
+            .iter()
+            .filter_map(|path| Some((path.parent()?, RecursiveMode::NonRecursive)))
+            .collect();
+        let (_watcher, receiver) = match watcher(&dirs) {
+            Ok(watcher) => watcher,
+            Err(error) => {
+                let _ = output.send(Err(error)).await;
+                return;
+            }
+        };
+
+        while let Ok(event) = receiver.recv().await {
+            let changed = match event {
+                Ok(event) => {
+                    !event.kind.is_access()
+                        && event
+                            .paths
+                            .iter()
+                            .any(|path| paths.iter().any(|config| config.as_std_path() == path))
+                }
+                Err(error) => {
+                    let _ = output.send(Err(Error::Watch(error.to_string()))).await;
+                    continue;
+                }
+            };
+
+            if changed {
+                let _ = output.send(Ok(())).await;
+            }
+        }
+    })
+}
+
+/// Hidden entries like `.git` and `.pokisona` are skipped like in the vault tree, except for the
+/// files git moves when the checked out commit changes
+fn is_watched(path: &Path) -> bool {
+    if path == ".git/HEAD" || path.starts_with(".git/refs/heads") {
+        return true;
+    }
+
+    !path
+        .components()
+        .any(|component| component.as_str().starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Path, watch::is_watched};
+
+    #[test]
+    fn test_is_watched() {
+        assert!(is_watched(Path::new("notes/idea.md")));
+        assert!(is_watched(Path::new(".git/HEAD")));
+        assert!(is_watched(Path::new(".git/refs/heads/main")));
+        assert!(!is_watched(Path::new(".git/objects/ab/cdef")));
+        assert!(!is_watched(Path::new(".pokisona/session.json")));
+        assert!(!is_watched(Path::new("notes/.hidden/idea.md")));
+    }
+}